@@ -1,11 +1,55 @@
 use std::{
     cell::RefCell,
-    cmp::Ordering,
     collections::{HashMap, VecDeque},
+    hash::{DefaultHasher, Hash, Hasher},
     rc::Rc,
 };
 
-use crate::data_type::*;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    data_type::*,
+    focus::{self, Focusable},
+    text::{self, BrokenParagraph, LineBreakEntry},
+};
+
+/// Label of the text element inside a text input.
+const TEXT_INPUT_TEXT: &str = "text";
+
+/// Z index of the bottom modal, each modal over it taking the next one.
+const MODAL_Z_INDEX: i16 = 1000;
+
+impl ElementId {
+    pub fn new(label: &str) -> ElementId {
+        let mut hasher = DefaultHasher::new();
+        label.hash(&mut hasher);
+        ElementId(hasher.finish())
+    }
+
+    /// Id for one of many elements sharing a label, such as the rows of a list.
+    pub fn indexed(label: &str, index: usize) -> ElementId {
+        let mut hasher = DefaultHasher::new();
+        label.hash(&mut hasher);
+        index.hash(&mut hasher);
+        ElementId(hasher.finish())
+    }
+
+    /// Id for an element inside the one with this id.
+    pub fn child(self, label: &str) -> ElementId {
+        let mut hasher = DefaultHasher::new();
+        self.0.hash(&mut hasher);
+        label.hash(&mut hasher);
+        ElementId(hasher.finish())
+    }
+
+    /// Id for one of many elements inside the one with this id, such as the options of a group.
+    pub fn nth_child(self, index: usize) -> ElementId {
+        let mut hasher = DefaultHasher::new();
+        self.0.hash(&mut hasher);
+        index.hash(&mut hasher);
+        ElementId(hasher.finish())
+    }
+}
 
 /**
  * TODO: REMOVE ALL POSSIBLE PANIC CODE WITH RESULT EQUIVALENT.
@@ -19,15 +63,47 @@ impl ElementConfig {
     pub fn new_from(config: Rc<ElementConfig>) -> Rc<ElementConfig> {
         Rc::new(*config)
     }
+
+    /**
+     * Flip everything that depends on the horizontal reading direction.
+     * Self alignment only flips when it lies on the horizontal axis, that is,
+     * when the parent lays its children out vertically.
+     */
+    pub fn mirrored(&self, parent_direction: LayoutDirection) -> ElementConfig {
+        ElementConfig {
+            padding: self.padding.mirrored(),
+            margin: self.margin.mirrored(),
+            child_alignment: AlignmentConfig {
+                align_x: self.child_alignment.align_x.mirrored(),
+                ..self.child_alignment
+            },
+            child_layout_direction: self.child_layout_direction.mirrored(),
+            self_alignment: match self.self_alignment {
+                Some(alignment) if !parent_direction.is_horizontal() => Some(alignment.mirrored()),
+                alignment => alignment,
+            },
+            ..*self
+        }
+    }
 }
 
 impl DimensionConfig {
     pub fn fit() -> DimensionConfig {
-        DimensionConfig::Fit(FitSizingConfig { min_size: 0. })
+        DimensionConfig::Fit(FitSizingConfig {
+            min_size: 0.,
+            max_size: 0.,
+        })
     }
 
     pub fn fit_clamped(min_size: f32) -> DimensionConfig {
-        DimensionConfig::Fit(FitSizingConfig { min_size })
+        DimensionConfig::Fit(FitSizingConfig {
+            min_size,
+            max_size: 0.,
+        })
+    }
+
+    pub fn fit_between(min_size: f32, max_size: f32) -> DimensionConfig {
+        DimensionConfig::Fit(FitSizingConfig { min_size, max_size })
     }
 
     pub fn grow() -> DimensionConfig {
@@ -42,15 +118,76 @@ impl DimensionConfig {
     }
 
     pub fn fixed(size: f32) -> DimensionConfig {
-        DimensionConfig::Fixed(FixedDimensionConfig { size })
+        DimensionConfig::Fixed(FixedDimensionConfig {
+            size,
+            min_size: 0.,
+            max_size: 0.,
+        })
+    }
+
+    pub fn fixed_clamped(size: f32, min_size: f32, max_size: f32) -> DimensionConfig {
+        DimensionConfig::Fixed(FixedDimensionConfig {
+            size,
+            min_size,
+            max_size,
+        })
     }
 
     pub fn percent(percent: f32) -> DimensionConfig {
-        DimensionConfig::Percent(PercentDimenionConfig { percent })
+        DimensionConfig::Percent(PercentDimenionConfig {
+            percent,
+            min_size: 0.,
+            max_size: 0.,
+        })
+    }
+
+    pub fn percent_clamped(percent: f32, min_size: f32, max_size: f32) -> DimensionConfig {
+        DimensionConfig::Percent(PercentDimenionConfig {
+            percent,
+            min_size,
+            max_size,
+        })
+    }
+
+    pub fn min_size(&self) -> f32 {
+        match self {
+            DimensionConfig::Fixed(conf) => conf.min_size,
+            DimensionConfig::Fit(conf) => conf.min_size,
+            DimensionConfig::Grow(conf) => conf.min_size,
+            DimensionConfig::Percent(conf) => conf.min_size,
+        }
+    }
+
+    pub fn max_size(&self) -> f32 {
+        match self {
+            DimensionConfig::Fixed(conf) => conf.max_size,
+            DimensionConfig::Fit(conf) => conf.max_size,
+            DimensionConfig::Grow(conf) => conf.max_size,
+            DimensionConfig::Percent(conf) => conf.max_size,
+        }
+    }
+
+    /// Clamp a size to the min and max of this config. A max of 0 is treated as unbounded.
+    pub fn clamp(&self, size: f32) -> f32 {
+        let size = size.max(self.min_size());
+
+        if self.max_size() != 0. {
+            size.min(self.max_size())
+        } else {
+            size
+        }
     }
 }
 
 impl PaddingConfig {
+    pub fn mirrored(&self) -> PaddingConfig {
+        PaddingConfig {
+            left: self.right,
+            right: self.left,
+            ..*self
+        }
+    }
+
     pub fn same_padding(padding: f32) -> PaddingConfig {
         PaddingConfig {
             left: padding,
@@ -88,12 +225,123 @@ impl PaddingConfig {
     }
 }
 
+impl MarginConfig {
+    pub fn mirrored(&self) -> MarginConfig {
+        MarginConfig {
+            left: self.right,
+            right: self.left,
+            ..*self
+        }
+    }
+
+    pub fn same_margin(margin: f32) -> MarginConfig {
+        MarginConfig {
+            left: margin,
+            right: margin,
+            top: margin,
+            bottom: margin,
+        }
+    }
+
+    pub fn axis_margin(top_bottom: f32, left_right: f32) -> MarginConfig {
+        MarginConfig {
+            left: left_right,
+            right: left_right,
+            top: top_bottom,
+            bottom: top_bottom,
+        }
+    }
+
+    pub fn individual_margin(top: f32, right: f32, bottom: f32, left: f32) -> MarginConfig {
+        MarginConfig {
+            top,
+            right,
+            bottom,
+            left,
+        }
+    }
+
+    pub fn no_margin() -> MarginConfig {
+        MarginConfig {
+            left: 0.,
+            right: 0.,
+            top: 0.,
+            bottom: 0.,
+        }
+    }
+}
+
 impl AlignmentConfig {
     pub fn new(align_x: HorizontalAlignment, align_y: VerticalAlignment) -> AlignmentConfig {
         AlignmentConfig { align_y, align_x }
     }
 }
 
+impl LayoutDirection {
+    pub fn is_horizontal(&self) -> bool {
+        matches!(
+            self,
+            LayoutDirection::LeftToRight | LayoutDirection::RightToLeft
+        )
+    }
+
+    pub fn is_reversed(&self) -> bool {
+        matches!(
+            self,
+            LayoutDirection::RightToLeft | LayoutDirection::BottomToTop
+        )
+    }
+
+    pub fn mirrored(&self) -> LayoutDirection {
+        match self {
+            LayoutDirection::LeftToRight => LayoutDirection::RightToLeft,
+            LayoutDirection::RightToLeft => LayoutDirection::LeftToRight,
+            direction => *direction,
+        }
+    }
+}
+
+impl HorizontalAlignment {
+    pub fn mirrored(&self) -> HorizontalAlignment {
+        match self {
+            HorizontalAlignment::Left => HorizontalAlignment::Right,
+            HorizontalAlignment::Right => HorizontalAlignment::Left,
+            HorizontalAlignment::Center => HorizontalAlignment::Center,
+        }
+    }
+}
+
+impl LineHeight {
+    /// The height of a line box, or `None` when lines are as tall as their content.
+    pub fn resolve(&self, font_size: u16) -> Option<f32> {
+        match self {
+            LineHeight::Auto => None,
+            LineHeight::Absolute(height) => Some(*height),
+            LineHeight::Multiplier(multiplier) => Some(font_size as f32 * multiplier),
+        }
+    }
+}
+
+impl TextAlignment {
+    pub fn mirrored(&self) -> TextAlignment {
+        match self {
+            TextAlignment::Left => TextAlignment::Right,
+            TextAlignment::Right => TextAlignment::Left,
+            alignment => *alignment,
+        }
+    }
+}
+
+impl SelfAlignment {
+    pub fn mirrored(&self) -> SelfAlignment {
+        match self {
+            SelfAlignment::Start => SelfAlignment::End,
+            SelfAlignment::End => SelfAlignment::Start,
+            alignment => *alignment,
+        }
+    }
+}
+
 impl Element {
     pub fn new(id: u64, element_config: TypeConfig) -> Element {
         Element {
@@ -105,6 +353,168 @@ impl Element {
             grow_on_percent_mark: false,
         }
     }
+
+    pub fn bounding_box(&self) -> BoundingBox {
+        BoundingBox {
+            x: self.position.x,
+            y: self.position.y,
+            width: self.dimensions.width,
+            height: self.dimensions.height,
+        }
+    }
+
+    pub fn descendant_count(&self) -> usize {
+        self.child_elements
+            .iter()
+            .map(|child| 1 + child.borrow().descendant_count())
+            .sum()
+    }
+
+    pub fn config_id(&self) -> Option<ElementId> {
+        match &self.element_config {
+            TypeConfig::Rectangle(rect_conf) => rect_conf.id,
+            TypeConfig::Text(text_conf) => text_conf.id,
+        }
+    }
+
+    pub fn width_config(&self) -> DimensionConfig {
+        match &self.element_config {
+            TypeConfig::Rectangle(rect_conf) => rect_conf.width,
+            TypeConfig::Text(text_conf) => text_conf.width,
+        }
+    }
+
+    pub fn height_config(&self) -> DimensionConfig {
+        match &self.element_config {
+            TypeConfig::Rectangle(rect_conf) => rect_conf.height,
+            TypeConfig::Text(text_conf) => text_conf.height,
+        }
+    }
+
+    /// Dimensions of the element including its margins.
+    pub fn outer_dimensions(&self) -> Dimensions {
+        let margin = self.margin();
+
+        Dimensions {
+            width: self.dimensions.width + margin.left + margin.right,
+            height: self.dimensions.height + margin.top + margin.bottom,
+        }
+    }
+
+    pub fn margin(&self) -> MarginConfig {
+        match &self.element_config {
+            TypeConfig::Rectangle(rect_conf) => rect_conf.margin,
+            TypeConfig::Text(text_conf) => text_conf.margin,
+        }
+    }
+
+    /**
+     * Whether the size on the given axis is derived from the other axis through the aspect ratio.
+     * A fixed height drives the width, otherwise the width (known after the width passes)
+     * drives the height. If both axes are fixed, the aspect ratio is ignored.
+     */
+    pub fn sized_by_aspect_ratio(&self, x_axis: bool) -> bool {
+        let TypeConfig::Rectangle(rect_conf) = &self.element_config else {
+            return false;
+        };
+
+        // a ratio that is not a positive number cannot size anything, so it is ignored.
+        if !rect_conf
+            .aspect_ratio
+            .is_some_and(|aspect_ratio| aspect_ratio > 0. && aspect_ratio.is_finite())
+        {
+            return false;
+        }
+
+        let fixed_width = matches!(rect_conf.width, DimensionConfig::Fixed(_));
+        let fixed_height = matches!(rect_conf.height, DimensionConfig::Fixed(_));
+
+        if x_axis {
+            fixed_height && !fixed_width
+        } else {
+            !fixed_height
+        }
+    }
+
+    pub fn self_alignment(&self) -> Option<SelfAlignment> {
+        match &self.element_config {
+            TypeConfig::Rectangle(rect_conf) => rect_conf.self_alignment,
+            TypeConfig::Text(text_conf) => text_conf.self_alignment,
+        }
+    }
+
+    /// Distance from the top of the element to its first baseline, or to its bottom edge without text.
+    pub fn baseline(&self) -> f32 {
+        match &self.element_config {
+            TypeConfig::Text(text_config) => text_config
+                .text_lines
+                .first()
+                .map_or(self.dimensions.height, |line| {
+                    text_config.vertical_offset(self.dimensions.height) + line.y + line.baseline
+                }),
+            TypeConfig::Rectangle(_) => self.dimensions.height,
+        }
+    }
+}
+
+impl AttachPoint {
+    /// How far along the width and the height of a box the point is.
+    pub fn fractions(self) -> (f32, f32) {
+        match self {
+            AttachPoint::LeftTop => (0., 0.),
+            AttachPoint::LeftCenter => (0., 0.5),
+            AttachPoint::LeftBottom => (0., 1.),
+            AttachPoint::CenterTop => (0.5, 0.),
+            AttachPoint::CenterCenter => (0.5, 0.5),
+            AttachPoint::CenterBottom => (0.5, 1.),
+            AttachPoint::RightTop => (1., 0.),
+            AttachPoint::RightCenter => (1., 0.5),
+            AttachPoint::RightBottom => (1., 1.),
+        }
+    }
+
+    /// The point on the opposite side, on both axes.
+    pub fn flipped(self) -> AttachPoint {
+        match self {
+            AttachPoint::LeftTop => AttachPoint::RightBottom,
+            AttachPoint::LeftCenter => AttachPoint::RightCenter,
+            AttachPoint::LeftBottom => AttachPoint::RightTop,
+            AttachPoint::CenterTop => AttachPoint::CenterBottom,
+            AttachPoint::CenterCenter => AttachPoint::CenterCenter,
+            AttachPoint::CenterBottom => AttachPoint::CenterTop,
+            AttachPoint::RightTop => AttachPoint::LeftBottom,
+            AttachPoint::RightCenter => AttachPoint::LeftCenter,
+            AttachPoint::RightBottom => AttachPoint::LeftTop,
+        }
+    }
+}
+
+impl BoundingBox {
+    pub fn contains(&self, position: Position) -> bool {
+        position.x >= self.x
+            && position.y >= self.y
+            && position.x < self.x + self.width
+            && position.y < self.y + self.height
+    }
+
+    /// The area covered by both boxes, empty (at the edge of one of them) when they do not meet.
+    pub fn intersection(&self, other: &BoundingBox) -> BoundingBox {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+
+        BoundingBox {
+            x,
+            y,
+            width: ((self.x + self.width).min(other.x + other.width) - x).max(0.),
+            height: ((self.y + self.height).min(other.y + other.height) - y).max(0.),
+        }
+    }
+}
+
+impl TextMeasurement {
+    pub fn height(&self) -> f32 {
+        self.ascent + self.descent
+    }
 }
 
 impl LayoutContext {
@@ -114,13 +524,39 @@ impl LayoutContext {
             element_stack: VecDeque::new(),
             top_id: 0,
             element_tree_post_order: Vec::new(),
+            floating_roots: Vec::new(),
             measure_text_fn: Box::new(|_, _, _| -> TextMeasurement {
                 panic!("No text measurement function was provided!")
             }),
             measure_text_cache: HashMap::new(),
+            line_break_cache: HashMap::new(),
+            element_map: HashMap::new(),
+            input_events: Vec::new(),
+            focused: None,
+            pointer: PointerState::default(),
+            frame_time: 0.,
+            hover_times: HashMap::new(),
+            hit_boxes: Vec::new(),
+            active: None,
+            open_menus: HashMap::new(),
+            modals: Vec::new(),
+            top_modal: None,
+            text_inputs: HashMap::new(),
+            selected_tabs: HashMap::new(),
+            virtual_lists: HashMap::new(),
+            mirrored: false,
         }
     }
 
+    /**
+     * Mirror the whole layout for right-to-left locales.
+     * Horizontal layout directions, horizontal alignments and left/right paddings
+     * are flipped as elements are declared, so every pass sees the mirrored configuration.
+     */
+    pub fn set_mirrored(&mut self, mirrored: bool) {
+        self.mirrored = mirrored;
+    }
+
     pub fn set_measurement_fn(
         &mut self,
         function: impl Fn(&str, u32, u16) -> TextMeasurement + 'static,
@@ -131,76 +567,132 @@ impl LayoutContext {
     pub fn begin_layout(&mut self) {
         self.element_stack.clear();
         self.element_tree_post_order.clear();
+        self.floating_roots.clear();
+        self.top_modal = self.modals.pop();
+        self.modals.clear();
         self.top_id = 1;
-        self.element_stack.push_back(Element::new(
-            0,
-            TypeConfig::Rectangle(Rc::new(ElementConfig {
-                width: DimensionConfig::fixed(self.root_dimensions.width),
-                height: DimensionConfig::fixed(self.root_dimensions.height),
-                ..Default::default()
-            })),
-        ));
+
+        // paragraphs that were not laid out during the last frame are forgotten.
+        self.line_break_cache
+            .retain(|_, entry| std::mem::take(&mut entry.used));
+
+        // tooltips that were not declared during the last frame start over.
+        self.hover_times
+            .retain(|_, hover_time| std::mem::take(&mut hover_time.declared));
+
+        // menus that were not declared during the last frame close.
+        self.open_menus
+            .retain(|_, menu| std::mem::take(&mut menu.declared));
+
+        // tab containers that were not declared during the last frame start on the first tab.
+        self.selected_tabs
+            .retain(|_, selection| std::mem::take(&mut selection.declared));
+
+        // virtual lists that were not declared during the last frame start over.
+        self.virtual_lists
+            .retain(|_, state| std::mem::take(&mut state.declared));
+
+        let mut root_config = ElementConfig {
+            width: DimensionConfig::fixed(self.root_dimensions.width),
+            height: DimensionConfig::fixed(self.root_dimensions.height),
+            ..Default::default()
+        };
+
+        if self.mirrored {
+            root_config = root_config.mirrored(LayoutDirection::LeftToRight);
+        }
+
+        self.element_stack
+            .push_back(Element::new(0, TypeConfig::Rectangle(Rc::new(root_config))));
+    }
+
+    fn parent_layout_direction(&self) -> LayoutDirection {
+        match &self
+            .element_stack
+            .back()
+            .expect("Any element must have a parent element.")
+            .element_config
+        {
+            TypeConfig::Rectangle(parent_config) => parent_config.child_layout_direction,
+            TypeConfig::Text(_) => panic!("Text element cannot have children."),
+        }
     }
 
     fn fit_sizing(&mut self, x_axis: bool) {
         for element in &self.element_tree_post_order {
             let mut element = element.borrow_mut();
 
+            if element.sized_by_aspect_ratio(x_axis) {
+                continue;
+            }
+
             match element.element_config.clone() {
                 TypeConfig::Rectangle(element_config) => {
                     let layout_direction = element_config.child_layout_direction;
 
                     if x_axis {
-                        if let DimensionConfig::Fit(fit_config) = element_config.width {
+                        if let DimensionConfig::Fit(_) = element_config.width {
                             match layout_direction {
-                                LayoutDirection::LeftToRight => {
+                                LayoutDirection::LeftToRight | LayoutDirection::RightToLeft => {
                                     let mut width_accumulator = 0.;
 
                                     for child in &element.child_elements {
-                                        width_accumulator += child.borrow().dimensions.width;
+                                        width_accumulator +=
+                                            child.borrow().outer_dimensions().width;
                                     }
 
                                     element.dimensions.width = width_accumulator;
 
                                     let gaps = element_config.child_gap;
                                     element.dimensions.width +=
-                                        (element.child_elements.len() - 1) as f32 * gaps;
-
-                                    // if the element is too small, clamp it to min_size
-                                    element.dimensions.width =
-                                        fit_config.min_size.max(element.dimensions.width);
+                                        element.child_elements.len().saturating_sub(1) as f32
+                                            * gaps;
                                 }
-                                LayoutDirection::TopToBottom => {
+                                LayoutDirection::TopToBottom | LayoutDirection::BottomToTop => {
                                     let mut max_width: f32 = 0.;
 
                                     for child in &element.child_elements {
-                                        max_width = max_width.max(child.borrow().dimensions.width);
+                                        max_width =
+                                            max_width.max(child.borrow().outer_dimensions().width);
                                     }
 
-                                    element.dimensions.width = max_width.max(fit_config.min_size);
+                                    element.dimensions.width = max_width;
                                 }
                             }
 
                             let padding_width =
                                 element_config.padding.left + element_config.padding.right;
                             element.dimensions.width += padding_width;
+
+                            // if the element is too small or too large, clamp it
+                            element.dimensions.width =
+                                element_config.width.clamp(element.dimensions.width);
                         }
-                    } else if let DimensionConfig::Fit(fit_config) = element_config.height {
+                    } else if let DimensionConfig::Fit(_) = element_config.height {
                         match layout_direction {
-                            LayoutDirection::LeftToRight => {
+                            LayoutDirection::LeftToRight | LayoutDirection::RightToLeft => {
                                 let mut max_height: f32 = 0.;
 
                                 for child in &element.child_elements {
-                                    max_height = max_height.max(child.borrow().dimensions.height);
+                                    max_height =
+                                        max_height.max(child.borrow().outer_dimensions().height);
                                 }
 
-                                element.dimensions.height = max_height.max(fit_config.min_size);
+                                // children sharing a baseline can stick out above and below each other.
+                                if let VerticalAlignment::Baseline =
+                                    element_config.child_alignment.align_y
+                                {
+                                    let (above, below) = baseline_extents(&element.child_elements);
+                                    max_height = max_height.max(above + below);
+                                }
+
+                                element.dimensions.height = max_height;
                             }
-                            LayoutDirection::TopToBottom => {
+                            LayoutDirection::TopToBottom | LayoutDirection::BottomToTop => {
                                 let mut height_accumulator = 0.;
 
                                 for child in &element.child_elements {
-                                    height_accumulator += child.borrow().dimensions.height;
+                                    height_accumulator += child.borrow().outer_dimensions().height;
                                 }
 
                                 element.dimensions.height = height_accumulator;
@@ -208,36 +700,28 @@ impl LayoutContext {
                                 let gap = element_config.child_gap;
 
                                 element.dimensions.height +=
-                                    (element.child_elements.len() - 1) as f32 * gap;
-
-                                element.dimensions.height =
-                                    fit_config.min_size.max(element.dimensions.height);
+                                    element.child_elements.len().saturating_sub(1) as f32 * gap;
                             }
                         }
 
                         let padding_height =
                             element_config.padding.top + element_config.padding.bottom;
                         element.dimensions.height += padding_height;
+
+                        element.dimensions.height =
+                            element_config.height.clamp(element.dimensions.height);
                     }
                 }
 
                 TypeConfig::Text(text_config) => {
-                    if x_axis {
-                        if let DimensionConfig::Fit(fit_config) = text_config.width {
-                            if fit_config.min_size != 0. {
-                                element.dimensions.width = fit_config.min_size
-                            }
-                        }
+                    if x_axis && let DimensionConfig::Fit(_) = text_config.width {
+                        element.dimensions.width = text_config.width.clamp(element.dimensions.width)
                     }
 
-                    // this is disabled as we do not have text overflow yet.
-                    /*
-                    else if let DimensionConfig::Fit(fit_config) = text_config.height {
-                        if fit_config.min_size != 0. {
-                            element.dimensions.height = fit_config.min_size;
-                        }
+                    if !x_axis && let DimensionConfig::Fit(_) = text_config.height {
+                        element.dimensions.height =
+                            text_config.height.clamp(element.dimensions.height)
                     }
-                    */
                 }
             }
         }
@@ -252,9 +736,11 @@ impl LayoutContext {
             let layout_direction;
             let horizontal_alignment;
             let vertical_alignment;
+            let clip;
 
             match &parent.element_config {
                 TypeConfig::Rectangle(element_config) => {
+                    clip = element_config.clip;
                     padding_config = element_config.padding;
                     child_gap = element_config.child_gap;
                     layout_direction = element_config.child_layout_direction;
@@ -275,68 +761,115 @@ impl LayoutContext {
 
             let mut childs_boundingbox = Dimensions::default();
 
+            // the shared baseline of a row, from the top of its content box.
+            let (row_baseline, _) = baseline_extents(&parent.child_elements);
+
             for child in &parent.child_elements {
                 let mut child = child.borrow_mut();
-                let child_dimensions = child.dimensions;
+                let child_dimensions = child.outer_dimensions();
+                let margin = child.margin();
 
                 match layout_direction {
-                    LayoutDirection::LeftToRight => {
+                    LayoutDirection::LeftToRight | LayoutDirection::RightToLeft => {
                         childs_boundingbox.width += child_dimensions.width;
 
+                        // the child's own alignment, if any, takes precedence over the parent's.
+                        let vertical_alignment = match child.self_alignment() {
+                            Some(SelfAlignment::Start | SelfAlignment::Stretch) => {
+                                VerticalAlignment::Top
+                            }
+                            Some(SelfAlignment::Center) => VerticalAlignment::Center,
+                            Some(SelfAlignment::End) => VerticalAlignment::Bottom,
+                            None => vertical_alignment,
+                        };
+
                         match vertical_alignment {
                             VerticalAlignment::Top => {
-                                child.position.y = parent.position.y + padding_config.top;
+                                child.position.y =
+                                    parent.position.y + padding_config.top + margin.top;
+                            }
+                            VerticalAlignment::Baseline => {
+                                child.position.y =
+                                    parent.position.y + padding_config.top + row_baseline
+                                        - child.baseline();
                             }
                             VerticalAlignment::Bottom => {
                                 child.position.y = parent.position.y + parent.dimensions.height
                                     - padding_config.bottom
+                                    - margin.bottom
                                     - child.dimensions.height;
                             }
                             VerticalAlignment::Center => {
                                 let height_offset = (parent.dimensions.height
-                                    - child.dimensions.height
+                                    - child_dimensions.height
                                     - padding_config.top
                                     - padding_config.bottom)
                                     / 2.;
-                                child.position.y = parent.position.y + height_offset;
+                                child.position.y = parent.position.y
+                                    + padding_config.top
+                                    + height_offset
+                                    + margin.top;
                             }
                         }
                     }
-                    LayoutDirection::TopToBottom => {
+                    LayoutDirection::TopToBottom | LayoutDirection::BottomToTop => {
                         childs_boundingbox.height += child_dimensions.height;
 
+                        let horizontal_alignment = match child.self_alignment() {
+                            Some(SelfAlignment::Start | SelfAlignment::Stretch) => {
+                                HorizontalAlignment::Left
+                            }
+                            Some(SelfAlignment::Center) => HorizontalAlignment::Center,
+                            Some(SelfAlignment::End) => HorizontalAlignment::Right,
+                            None => horizontal_alignment,
+                        };
+
                         match horizontal_alignment {
                             HorizontalAlignment::Left => {
-                                child.position.x = parent.position.x + padding_config.left;
+                                child.position.x =
+                                    parent.position.x + padding_config.left + margin.left;
                             }
                             HorizontalAlignment::Right => {
                                 child.position.x = parent.position.x + parent.dimensions.width
                                     - padding_config.right
+                                    - margin.right
                                     - child.dimensions.width;
                             }
                             HorizontalAlignment::Center => {
                                 let width_offset = (parent.dimensions.width
-                                    - child.dimensions.width
+                                    - child_dimensions.width
                                     - padding_config.left
                                     - padding_config.right)
                                     / 2.;
 
-                                child.position.x = parent.position.x + width_offset;
+                                child.position.x = parent.position.x
+                                    + padding_config.left
+                                    + width_offset
+                                    + margin.left;
                             }
                         }
                     }
                 }
             }
 
+            // reversed directions flow the same way, only starting from the last child.
+            let mut ordered_children: Vec<&ElementReference> =
+                parent.child_elements.iter().collect();
+            if layout_direction.is_reversed() {
+                ordered_children.reverse();
+            }
+
             match layout_direction {
-                LayoutDirection::LeftToRight => {
-                    childs_boundingbox.width += parent.child_elements.len() as f32 * child_gap;
+                LayoutDirection::LeftToRight | LayoutDirection::RightToLeft => {
+                    childs_boundingbox.width +=
+                        parent.child_elements.len().saturating_sub(1) as f32 * child_gap;
 
                     let mut offset = 0.;
                     let start_x = match horizontal_alignment {
                         HorizontalAlignment::Left => parent.position.x + padding_config.left,
                         HorizontalAlignment::Center => {
                             parent.position.x
+                                + padding_config.left
                                 + (parent.dimensions.width
                                     - childs_boundingbox.width
                                     - padding_config.left
@@ -350,21 +883,27 @@ impl LayoutContext {
                         }
                     };
 
-                    for child in &parent.child_elements {
+                    for child in &ordered_children {
                         let mut child = child.borrow_mut();
 
-                        child.position.x = start_x + offset;
-                        offset += child.dimensions.width + child_gap;
+                        let margin = child.margin();
+
+                        child.position.x = start_x + offset + margin.left;
+                        offset += child.outer_dimensions().width + child_gap;
                     }
                 }
-                LayoutDirection::TopToBottom => {
-                    childs_boundingbox.height += parent.child_elements.len() as f32 * child_gap;
+                LayoutDirection::TopToBottom | LayoutDirection::BottomToTop => {
+                    childs_boundingbox.height +=
+                        parent.child_elements.len().saturating_sub(1) as f32 * child_gap;
 
                     let mut offset = 0.;
                     let start_y = match vertical_alignment {
-                        VerticalAlignment::Top => parent.position.y + padding_config.top,
+                        VerticalAlignment::Top | VerticalAlignment::Baseline => {
+                            parent.position.y + padding_config.top
+                        }
                         VerticalAlignment::Center => {
                             parent.position.y
+                                + padding_config.top
                                 + (parent.dimensions.height
                                     - childs_boundingbox.height
                                     - padding_config.top
@@ -378,14 +917,25 @@ impl LayoutContext {
                         }
                     };
 
-                    for child in &parent.child_elements {
+                    for child in &ordered_children {
                         let mut child = child.borrow_mut();
 
-                        child.position.y = start_y + offset;
-                        offset += child.dimensions.height + child_gap;
+                        let margin = child.margin();
+
+                        child.position.y = start_y + offset + margin.top;
+                        offset += child.outer_dimensions().height + child_gap;
                     }
                 }
             }
+
+            // the content moves as a whole, the children's own children follow from there.
+            if let Some(clip) = clip {
+                for child in &parent.child_elements {
+                    let mut child = child.borrow_mut();
+                    child.position.x += clip.child_offset.x;
+                    child.position.y += clip.child_offset.y;
+                }
+            }
         }
     }
 
@@ -394,85 +944,52 @@ impl LayoutContext {
             let parent = element.borrow();
 
             if parent.child_elements.is_empty() {
-                return;
+                continue;
             }
 
             if let TypeConfig::Rectangle(parent_config) = &parent.element_config {
-                let parent_undefined_size = matches!(parent_config.width, DimensionConfig::Grow(_));
+                let parent_undefined_size = if x_axis {
+                    matches!(parent_config.width, DimensionConfig::Grow(_))
+                } else {
+                    matches!(parent_config.height, DimensionConfig::Grow(_))
+                };
                 let child_count = parent.child_elements.len();
 
                 for child_index in 0..child_count {
                     let mut child = parent.child_elements[child_index].borrow_mut();
 
+                    let size_config = if x_axis {
+                        child.width_config()
+                    } else {
+                        child.height_config()
+                    };
+
+                    let DimensionConfig::Percent(percent_config) = size_config else {
+                        continue;
+                    };
+
+                    if child.sized_by_aspect_ratio(x_axis) {
+                        continue;
+                    }
+
                     if parent_undefined_size {
                         child.grow_on_percent_mark = true;
                         continue;
                     }
 
-                    if x_axis {
-                        let width_config = match &child.element_config {
-                            TypeConfig::Rectangle(child_config) => child_config.width,
-                            TypeConfig::Text(child_text_config) => child_text_config.width,
-                        };
-
-                        if let DimensionConfig::Percent(percent_config) = width_config {
-                            child.dimensions.width =
-                                parent.dimensions.width * percent_config.percent;
-
-                            if child_index == 0 {
-                                child.dimensions.width -= parent_config.padding.left;
-                            }
-
-                            if child_index == child_count - 1 {
-                                child.dimensions.width -= parent_config.padding.right;
-                            }
+                    let size = percent_size(
+                        &parent,
+                        parent_config,
+                        &child,
+                        child_index,
+                        percent_config.percent,
+                        x_axis,
+                    );
 
-                            if child_count > 1
-                                && matches!(
-                                    parent_config.child_layout_direction,
-                                    LayoutDirection::LeftToRight
-                                )
-                            {
-                                child.dimensions.width -= parent_config.child_gap
-                                    / if child_index == 0 || child_index == child_count - 1 {
-                                        2.
-                                    } else {
-                                        1.
-                                    };
-                            }
-                        }
+                    if x_axis {
+                        child.dimensions.width = size;
                     } else {
-                        let height_config = match &child.element_config {
-                            TypeConfig::Rectangle(child_config) => child_config.height,
-                            TypeConfig::Text(child_text_config) => child_text_config.height,
-                        };
-
-                        if let DimensionConfig::Percent(percent_config) = height_config {
-                            child.dimensions.height =
-                                parent.dimensions.height * percent_config.percent;
-
-                            if child_index == 0 {
-                                child.dimensions.height -= parent_config.padding.top;
-                            }
-
-                            if child_index == child_count - 1 {
-                                child.dimensions.height -= parent_config.padding.bottom;
-                            }
-
-                            if child_count > 1
-                                && matches!(
-                                    parent_config.child_layout_direction,
-                                    LayoutDirection::TopToBottom
-                                )
-                            {
-                                child.dimensions.height -= parent_config.child_gap
-                                    / if child_index == 0 || child_index == child_count - 1 {
-                                        2.
-                                    } else {
-                                        1.
-                                    };
-                            }
-                        }
+                        child.dimensions.height = size;
                     }
                 }
             }
@@ -512,93 +1029,47 @@ impl LayoutContext {
                for awaiting a concrete grow value can (and must) be solved here.
             */
 
-            let child_count = parent.child_elements.len();
-            let half_gap = parent_config.child_gap / 2.;
+            let parent_grow = if x_axis {
+                matches!(parent_config.width, DimensionConfig::Grow(_))
+            } else {
+                matches!(parent_config.height, DimensionConfig::Grow(_))
+            };
 
-            if x_axis {
-                if let DimensionConfig::Grow(_) = parent_config.width {
-                    for child_index in 0..child_count {
-                        let mut child = parent.child_elements[child_index].borrow_mut();
-                        if !child.grow_on_percent_mark {
-                            continue;
-                        }
+            if parent_grow {
+                for child_index in 0..parent.child_elements.len() {
+                    let mut child = parent.child_elements[child_index].borrow_mut();
+                    if !child.grow_on_percent_mark {
+                        continue;
+                    }
 
-                        let width_config = match &child.element_config {
-                            TypeConfig::Rectangle(rect_conf) => rect_conf.width,
-                            TypeConfig::Text(text_conf) => text_conf.width,
-                        };
+                    // the mark is shared by both axes, so the other axis may not be a percent.
+                    let size_config = if x_axis {
+                        child.width_config()
+                    } else {
+                        child.height_config()
+                    };
 
-                        let width_percentage = match width_config {
-                            DimensionConfig::Percent(percentage_conf) => percentage_conf.percent,
-                            _ => {
-                                panic!("There is no other sizing type that depends on parent here")
-                            }
-                        };
+                    let DimensionConfig::Percent(percent_config) = size_config else {
+                        continue;
+                    };
 
-                        child.dimensions.width = parent.dimensions.width * width_percentage;
-
-                        if child_index == 0 {
-                            child.dimensions.width -= parent_config.padding.left;
-                        }
-
-                        if child_index == child_count - 1 {
-                            child.dimensions.width -= parent_config.padding.right;
-                        }
-
-                        if child_count > 1
-                            && matches!(
-                                parent_config.child_layout_direction,
-                                LayoutDirection::LeftToRight
-                            )
-                        {
-                            if child_index == 0 || child_index == child_count - 1 {
-                                child.dimensions.width -= half_gap;
-                            } else {
-                                child.dimensions.width -= 2. * half_gap;
-                            }
-                        }
-                    }
-                }
-            } else if let DimensionConfig::Grow(_) = parent_config.height {
-                for child_index in 0..child_count {
-                    let mut child = parent.child_elements[child_index].borrow_mut();
-                    if !child.grow_on_percent_mark {
+                    if child.sized_by_aspect_ratio(x_axis) {
                         continue;
                     }
 
-                    let height_config = match &child.element_config {
-                        TypeConfig::Rectangle(rect_conf) => rect_conf.height,
-                        TypeConfig::Text(text_conf) => text_conf.height,
-                    };
-
-                    let height_percentage = match height_config {
-                        DimensionConfig::Percent(percentage_conf) => percentage_conf.percent,
-                        _ => {
-                            panic!("There is no other sizing type that depends on parent here")
-                        }
-                    };
-
-                    child.dimensions.height = parent.dimensions.height * height_percentage;
-
-                    if child_index == 0 {
-                        child.dimensions.height -= parent_config.padding.top;
-                    }
-
-                    if child_index == child_count - 1 {
-                        child.dimensions.height -= parent_config.padding.bottom;
-                    }
+                    let size = percent_size(
+                        &parent,
+                        parent_config,
+                        &child,
+                        child_index,
+                        percent_config.percent,
+                        x_axis,
+                    );
 
-                    if child_count > 1
-                        && matches!(
-                            parent_config.child_layout_direction,
-                            LayoutDirection::TopToBottom
-                        )
-                    {
-                        if child_index == 0 || child_index == child_count - 1 {
-                            child.dimensions.height -= half_gap;
-                        } else {
-                            child.dimensions.height -= 2. * half_gap;
-                        }
+                    if x_axis {
+                        child.dimensions.width = size;
+                    } else {
+                        child.dimensions.height = size;
                     }
                 }
             }
@@ -618,10 +1089,8 @@ impl LayoutContext {
                     - parent_config.padding.left
                     - parent_config.padding.right;
 
-                if matches!(
-                    parent_config.child_layout_direction,
-                    LayoutDirection::LeftToRight
-                ) && parent.child_elements.len() > 1
+                if parent_config.child_layout_direction.is_horizontal()
+                    && parent.child_elements.len() > 1
                 {
                     remaining_dimensions -=
                         parent_config.child_gap * (parent.child_elements.len() - 1) as f32;
@@ -631,10 +1100,8 @@ impl LayoutContext {
                     - parent_config.padding.top
                     - parent_config.padding.bottom;
 
-                if matches!(
-                    parent_config.child_layout_direction,
-                    LayoutDirection::TopToBottom
-                ) && parent.child_elements.len() > 1
+                if !parent_config.child_layout_direction.is_horizontal()
+                    && parent.child_elements.len() > 1
                 {
                     remaining_dimensions -=
                         parent_config.child_gap * (parent.child_elements.len() - 1) as f32;
@@ -643,370 +1110,394 @@ impl LayoutContext {
 
             for child_ref in &parent.child_elements {
                 let mut child = child_ref.borrow_mut();
+                let margin = child.margin();
+
+                // the size on this axis is already derived from the other one, treat it as fixed.
+                if child.sized_by_aspect_ratio(x_axis) {
+                    if x_axis && parent_config.child_layout_direction.is_horizontal() {
+                        remaining_dimensions -= child.outer_dimensions().width;
+                    }
+
+                    if !x_axis && !parent_config.child_layout_direction.is_horizontal() {
+                        remaining_dimensions -= child.outer_dimensions().height;
+                    }
+
+                    continue;
+                }
+
+                // stretched children take the whole cross axis, whatever their own sizing is.
+                if matches!(child.self_alignment(), Some(SelfAlignment::Stretch)) {
+                    if x_axis && !parent_config.child_layout_direction.is_horizontal() {
+                        child.dimensions.width = child
+                            .width_config()
+                            .clamp(remaining_dimensions - margin.left - margin.right);
+                        continue;
+                    }
+
+                    if !x_axis && parent_config.child_layout_direction.is_horizontal() {
+                        child.dimensions.height = child
+                            .height_config()
+                            .clamp(remaining_dimensions - margin.top - margin.bottom);
+                        continue;
+                    }
+                }
 
                 if x_axis {
-                    let width_config = match &child.element_config {
-                        TypeConfig::Rectangle(rect_conf) => rect_conf.width,
-                        TypeConfig::Text(text_conf) => text_conf.width,
-                    };
+                    let width_config = child.width_config();
 
                     if let DimensionConfig::Grow(_) = width_config {
-                        if matches!(
-                            parent_config.child_layout_direction,
-                            LayoutDirection::TopToBottom
-                        ) {
-                            child.dimensions.width = remaining_dimensions;
+                        if !parent_config.child_layout_direction.is_horizontal() {
+                            child.dimensions.width = width_config
+                                .clamp(remaining_dimensions - margin.left - margin.right);
                             continue;
                         }
 
                         grow_child_vec.push(Rc::clone(child_ref));
-                        remaining_dimensions -= child.dimensions.width;
+                        remaining_dimensions -= child.outer_dimensions().width;
                         continue;
                     }
 
-                    if matches!(
-                        parent_config.child_layout_direction,
-                        LayoutDirection::LeftToRight
-                    ) {
-                        remaining_dimensions -= child.dimensions.width;
+                    if parent_config.child_layout_direction.is_horizontal() {
+                        remaining_dimensions -= child.outer_dimensions().width;
                     }
                 } else {
-                    let height_config = match &child.element_config {
-                        TypeConfig::Rectangle(rect_conf) => rect_conf.height,
-                        TypeConfig::Text(text_conf) => text_conf.height,
-                    };
+                    let height_config = child.height_config();
 
                     if let DimensionConfig::Grow(_) = height_config {
-                        if matches!(
-                            parent_config.child_layout_direction,
-                            LayoutDirection::LeftToRight
-                        ) {
-                            child.dimensions.height = remaining_dimensions;
+                        if parent_config.child_layout_direction.is_horizontal() {
+                            child.dimensions.height = height_config
+                                .clamp(remaining_dimensions - margin.top - margin.bottom);
                             continue;
                         }
 
                         grow_child_vec.push(Rc::clone(child_ref));
-                        remaining_dimensions -= child.dimensions.height;
+                        remaining_dimensions -= child.outer_dimensions().height;
                         continue;
                     }
 
-                    if matches!(
-                        parent_config.child_layout_direction,
-                        LayoutDirection::TopToBottom
-                    ) {
-                        remaining_dimensions -= child.dimensions.height;
+                    if !parent_config.child_layout_direction.is_horizontal() {
+                        remaining_dimensions -= child.outer_dimensions().height;
                     }
                 }
             }
 
-            // Sort all children that need to solve grow for by their current size.
-            // We grow these child until they have the same size, then distribute the rest evenly.
-            grow_child_vec.sort_by(|a, b| -> Ordering {
-                let a = a.borrow();
-                let b = b.borrow();
-
-                if x_axis {
-                    if a.dimensions.width > b.dimensions.width {
-                        return Ordering::Greater;
-                    }
-
-                    if a.dimensions.width < b.dimensions.width {
-                        return Ordering::Less;
-                    }
-
-                    Ordering::Equal
-                } else {
-                    if a.dimensions.height > b.dimensions.height {
-                        return Ordering::Greater;
-                    }
+            distribute_grow(grow_child_vec, remaining_dimensions, x_axis);
+        }
+    }
 
-                    if a.dimensions.height < b.dimensions.height {
-                        return Ordering::Less;
-                    }
+    fn aspect_ratio_sizing(&mut self) {
+        for element in &self.element_tree_post_order {
+            let mut element = element.borrow_mut();
 
-                    Ordering::Equal
-                }
-            });
+            if !element.sized_by_aspect_ratio(false) {
+                continue;
+            }
 
-            let mut min_sizing: f32 = 0.;
-            let mut index = 0;
+            if let TypeConfig::Rectangle(element_config) = &element.element_config
+                && let Some(aspect_ratio) = element_config.aspect_ratio
+            {
+                element.dimensions.height = element.dimensions.width / aspect_ratio;
+            }
+        }
+    }
 
-            // grow all childs to the biggest child.
-            while index < grow_child_vec.len() {
-                if x_axis {
-                    if matches!(
-                        parent_config.child_layout_direction,
-                        LayoutDirection::TopToBottom
-                    ) {
-                        {
-                            let mut element = grow_child_vec[index].borrow_mut();
-                            element.dimensions.width += remaining_dimensions;
-                        }
-                        grow_child_vec.remove(index);
-                        continue;
-                    }
+    fn wrap_text(&mut self) {
+        for element in &self.element_tree_post_order {
+            let mut element = element.borrow_mut();
+            let text_config = match &element.element_config {
+                TypeConfig::Text(text_config) => text_config,
+                _ => continue,
+            };
 
-                    let element_size = grow_child_vec[index].borrow().dimensions.width;
+            let mut text_lines: Vec<TextLayoutLine> = Vec::new();
 
-                    if element_size > min_sizing {
-                        min_sizing = element_size;
+            // Since this is an immediate mode layout, a simple greedy text-breaking will suffice
+            // by default. Optimal breaking is opt-in, for long-form text.
+            let font_id = text_config.font_id;
+            let font_size = text_config.font_size;
+            let letter_spacing = text_config.letter_spacing;
+            let line_height = text_config.line_height.resolve(font_size);
+            let text_alignment = text_config.text_alignment;
+            let paragraph_spacing = text_config.paragraph_spacing;
+            let mirrored = self.mirrored;
+
+            // text that does not wrap still breaks at hard line breaks.
+            let max_width = if text_config.break_word {
+                element.dimensions.width
+            } else {
+                f32::INFINITY
+            };
 
-                        if (remaining_dimensions / (index + 1) as f32) < min_sizing {
-                            min_sizing = remaining_dimensions / (index + 1) as f32;
+            let space_measurement = get_measurement(
+                &mut self.measure_text_cache,
+                &self.measure_text_fn,
+                " ",
+                font_id,
+                font_size,
+                letter_spacing,
+            );
+            let tab_stop = space_measurement.width * text_config.tab_size as f32;
+            let text = text_config.text.clone();
 
-                            // grow each element toward the final min_sizing
-                            let mut id = 0;
-                            while id < index {
-                                let mut element = grow_child_vec[id].borrow_mut();
-                                let width_config = match &element.element_config {
-                                    TypeConfig::Rectangle(rect_conf) => rect_conf.width,
-                                    TypeConfig::Text(text_conf) => text_conf.width,
-                                };
-                                if let DimensionConfig::Grow(width_config) = width_config {
-                                    let max_val = width_config.max_size;
+            let spans = &text_config.spans;
 
-                                    // clamp the grow to the max_value, if applicable
-                                    if max_val != 0. {
-                                        let delta = max_val - element.dimensions.width;
-                                        remaining_dimensions -= delta;
-                                        element.dimensions.width = max_val;
+            let mut measure = |word: &str, span: usize| {
+                get_measurement(
+                    &mut self.measure_text_cache,
+                    &self.measure_text_fn,
+                    word,
+                    spans[span].font_id,
+                    spans[span].font_size,
+                    letter_spacing,
+                )
+            };
 
-                                        drop(element);
+            let mut height_offset = 0.;
 
-                                        grow_child_vec.remove(id);
-                                        index -= 1;
-                                        continue;
-                                    }
+            for (paragraph_index, paragraph) in
+                text::paragraphs(&text, spans, text_config.white_space, mirrored)
+                    .iter()
+                    .enumerate()
+            {
+                if paragraph_index != 0 {
+                    height_offset += paragraph_spacing;
+                }
 
-                                    element.dimensions.width = min_sizing;
-                                    id += 1;
-                                }
+                // a line without words is as tall as a space of the span it starts in.
+                let empty_line_measurement = measure(" ", paragraph.runs[0].span);
+
+                let mut tokenize = || {
+                    let tokens = text::tokenize(paragraph, &mut measure);
+                    text::break_long_words(
+                        &paragraph.text,
+                        tokens,
+                        max_width,
+                        text_config.overflow_wrap,
+                        &mut measure,
+                    )
+                };
+
+                let broken_paragraph = match text_config.line_breaking {
+                    // text that does not wrap has nothing to optimize.
+                    LineBreaking::Optimal if max_width.is_finite() => {
+                        let key = text::line_break_key(
+                            paragraph,
+                            spans,
+                            letter_spacing,
+                            max_width,
+                            tab_stop,
+                            text_config.overflow_wrap,
+                        );
+                        let entry = self.line_break_cache.entry(key).or_insert_with(|| {
+                            let tokens = tokenize();
+                            let breaks = text::optimal_breaks(&tokens, max_width, tab_stop);
+                            let lines = text::lines_from_breaks(
+                                &tokens,
+                                &breaks,
+                                tab_stop,
+                                empty_line_measurement,
+                            );
+
+                            LineBreakEntry {
+                                paragraph: Rc::new(BrokenParagraph { tokens, lines }),
+                                used: false,
                             }
-                            break;
-                        }
-
-                        // grow each element fairly to the min_sizing
-                        let mut id = 0;
-                        while id < index {
-                            let mut element = grow_child_vec[id].borrow_mut();
-                            let width_config = match &element.element_config {
-                                TypeConfig::Rectangle(rect_conf) => rect_conf.width,
-                                TypeConfig::Text(text_conf) => text_conf.width,
-                            };
-                            if let DimensionConfig::Grow(width_config) = width_config {
-                                let max_size = width_config.max_size;
-                                // clamp the grow to the max_value, if applicable
-                                if max_size != 0. {
-                                    let delta = max_size - element.dimensions.width;
-                                    remaining_dimensions -= delta;
-                                    element.dimensions.width = max_size;
-
-                                    drop(element);
-
-                                    grow_child_vec.remove(id);
-                                    index -= 1;
-                                    id += 1;
-                                    continue;
-                                }
-
-                                let delta = min_sizing - element.dimensions.width;
+                        });
+                        entry.used = true;
 
-                                element.dimensions.width = min_sizing;
-                                remaining_dimensions -= delta;
-                                id += 1;
-                            }
-                        }
-                    }
-                } else {
-                    if matches!(
-                        parent_config.child_layout_direction,
-                        LayoutDirection::LeftToRight
-                    ) {
-                        {
-                            let mut element = grow_child_vec[index].borrow_mut();
-                            element.dimensions.height += remaining_dimensions;
-                        }
-                        grow_child_vec.remove(index);
-                        continue;
+                        entry.paragraph.clone()
                     }
+                    _ => {
+                        let tokens = tokenize();
+                        let lines =
+                            text::break_lines(&tokens, max_width, tab_stop, empty_line_measurement);
 
-                    let element_size = grow_child_vec[index].borrow().dimensions.height;
-
-                    if element_size > min_sizing {
-                        min_sizing = element_size;
-
-                        if (remaining_dimensions / (index + 1) as f32) < min_sizing {
-                            min_sizing = remaining_dimensions / (index + 1) as f32;
-
-                            // grow each element toward the final min_sizing
-                            let mut id = 0;
-                            while id < index {
-                                let mut element = grow_child_vec[id].borrow_mut();
-                                let height_config = match &element.element_config {
-                                    TypeConfig::Rectangle(rect_conf) => rect_conf.height,
-                                    TypeConfig::Text(text_conf) => text_conf.height,
-                                };
-                                if let DimensionConfig::Grow(height_config) = height_config {
-                                    let max_size = height_config.max_size;
-
-                                    // clamp the grow to the max_value, if applicable
-                                    if max_size != 0. {
-                                        let delta = max_size - element.dimensions.height;
-                                        remaining_dimensions -= delta;
-                                        element.dimensions.height = max_size;
-
-                                        drop(element);
+                        Rc::new(BrokenParagraph { tokens, lines })
+                    }
+                };
+                let BrokenParagraph { tokens, lines } = &*broken_paragraph;
+                let line_count = lines.len();
 
-                                        grow_child_vec.remove(id);
-                                        index -= 1;
-                                        continue;
-                                    }
+                // fragments share their paragraph, to find positions in it.
+                let paragraph_text: Rc<str> = Rc::from(paragraph.text.as_str());
+                let paragraph_source: Rc<[usize]> = Rc::from(paragraph.source.as_slice());
 
-                                    element.dimensions.height = min_sizing;
-                                    id += 1;
-                                }
-                            }
+                // alignment follows the paragraph's direction, mirrored layouts already swapped it.
+                let text_alignment = if paragraph.level.is_rtl() != mirrored {
+                    text_alignment.mirrored()
+                } else {
+                    text_alignment
+                };
+
+                for (line_index, line) in lines.iter().enumerate() {
+                    let mut layout_line = TextLayoutLine {
+                        y: height_offset,
+                        height: line.ascent + line.descent,
+                        baseline: line.ascent,
+                        alignment: text_alignment,
+                        rtl: paragraph.level.is_rtl(),
+                        range: 0..0,
+                        fragments: Vec::new(),
+                    };
 
-                            break;
-                        }
+                    // a set line height spreads the leading evenly above and below the text.
+                    if let Some(line_height) = line_height {
+                        layout_line.baseline += (line_height - layout_line.height) / 2.;
+                        layout_line.height = line_height;
+                    }
 
-                        // grow each element fairly to the min_sizing
-                        let mut id = 0;
-                        while id < index {
-                            let mut element = grow_child_vec[id].borrow_mut();
-                            let height_config = match &element.element_config {
-                                TypeConfig::Rectangle(rect_conf) => rect_conf.height,
-                                TypeConfig::Text(text_conf) => text_conf.height,
+                    height_offset += layout_line.height;
+
+                    // the last line of a justified paragraph keeps its natural spacing.
+                    let justify = matches!(text_alignment, TextAlignment::Justify)
+                        && line_index != line_count - 1
+                        && text::word_count(tokens, line) > 1;
+
+                    let (line_x, pieces) = if justify {
+                        (
+                            0.,
+                            text::justified_pieces(
+                                tokens,
+                                line,
+                                paragraph.level,
+                                tab_stop,
+                                element.dimensions.width,
+                            ),
+                        )
+                    } else {
+                        // lines that are not stretched stay at the start of the paragraph.
+                        if let TextAlignment::Justify = layout_line.alignment {
+                            layout_line.alignment = if paragraph.level.is_rtl() {
+                                TextAlignment::Right
+                            } else {
+                                TextAlignment::Left
                             };
-                            if let DimensionConfig::Grow(height_config) = height_config {
-                                let max_val = height_config.max_size;
-
-                                // clamp the grow to the max_value, if applicable
-                                if max_val != 0. {
-                                    let delta = max_val - element.dimensions.height;
-                                    remaining_dimensions -= delta;
-                                    element.dimensions.height = max_val;
-
-                                    drop(element);
+                        }
 
-                                    grow_child_vec.remove(id);
-                                    index -= 1;
-                                    id += 1;
-                                    continue;
-                                }
+                        let line_x = match layout_line.alignment {
+                            TextAlignment::Left | TextAlignment::Justify => 0.,
+                            TextAlignment::Center => (element.dimensions.width - line.width) / 2.,
+                            TextAlignment::Right => element.dimensions.width - line.width,
+                        };
 
-                                let delta = min_sizing - element.dimensions.height;
+                        (
+                            line_x,
+                            text::line_pieces(tokens, line, paragraph.level, tab_stop),
+                        )
+                    };
 
-                                element.dimensions.height = min_sizing;
-                                remaining_dimensions -= delta;
-                                id += 1;
-                            }
-                        }
+                    for piece in pieces {
+                        layout_line.fragments.push(TextFragment {
+                            text: Rc::from(text::piece_text(&paragraph.text, &piece)),
+                            span: piece.span,
+                            range: paragraph.source[piece.range.start]
+                                ..paragraph.source[piece.range.end],
+                            paragraph: paragraph_text.clone(),
+                            source: paragraph_source.clone(),
+                            paragraph_range: piece.range,
+                            rtl: piece.rtl,
+                            x: line_x + piece.x,
+                            width: piece.width,
+                        });
                     }
-                }
 
-                index += 1;
+                    // fragments are in visual order, the line covers all of them.
+                    let line_start = tokens
+                        .get(line.tokens.start)
+                        .map_or(paragraph.source[0], |token| {
+                            paragraph.source[token.range.start]
+                        });
+                    layout_line.range = layout_line
+                        .fragments
+                        .iter()
+                        .map(|fragment| fragment.range.clone())
+                        .reduce(|a, b| a.start.min(b.start)..a.end.max(b.end))
+                        .unwrap_or(line_start..line_start);
+
+                    text_lines.push(layout_line);
+                }
             }
 
-            // distribute remaining value equally
-            remaining_dimensions /= grow_child_vec.len() as f32;
+            let line_limit = match text_config.max_lines {
+                0 => text_lines.len(),
+                max_lines => max_lines,
+            };
 
-            for element in &grow_child_vec {
-                let mut element = element.borrow_mut();
+            text::truncate_lines(
+                &mut text_lines,
+                line_limit,
+                element.dimensions.width,
+                &text_config.text_overflow,
+                measure,
+            );
 
-                if x_axis {
-                    element.dimensions.width += remaining_dimensions;
-                } else {
-                    element.dimensions.height += remaining_dimensions;
+            // fit and grow text starts from its content, percent text is sized by its parent later.
+            let content_height = text_lines.last().map_or(0., |line| line.y + line.height);
+            element.dimensions.height = match text_config.height {
+                DimensionConfig::Fixed(fixed_config) => text_config.height.clamp(fixed_config.size),
+                DimensionConfig::Fit(_) | DimensionConfig::Grow(_) => {
+                    text_config.height.clamp(content_height)
                 }
+                DimensionConfig::Percent(_) => content_height,
+            };
+
+            if let TypeConfig::Text(text_config) = &mut element.element_config {
+                text_config.text_lines = text_lines;
             }
         }
     }
 
-    fn wrap_text(&mut self) {
+    /**
+     * Hide the lines of text elements that do not fit their final height.
+     * This has to wait for the height passes, as text can be sized by its parent.
+     */
+    fn truncate_text(&mut self) {
+        // lines are only hidden once they overflow by more than rounding errors.
+        const EPSILON: f32 = 0.01;
+
         for element in &self.element_tree_post_order {
             let mut element = element.borrow_mut();
-            let text_config = match &element.element_config {
-                TypeConfig::Text(text_config) => text_config,
-                _ => continue,
-            };
+            let element_dimensions = element.dimensions;
 
-            let mut text_lines: Vec<Rc<Element>> = Vec::new();
+            let TypeConfig::Text(text_config) = &mut element.element_config else {
+                continue;
+            };
 
-            if !text_config.break_word {
+            if matches!(text_config.text_overflow, TextOverflow::Visible) {
                 continue;
             }
 
-            // Since this is an immediate mode layout, a simple greedy text-breaking will suffice.
-            let font_id = text_config.font_id;
-            let font_size = text_config.font_size;
-            let space_measurement = get_measurement(
-                &mut self.measure_text_cache,
-                &self.measure_text_fn,
-                " ",
-                font_id,
-                font_size,
-            );
-            let text = text_config.text.clone();
-            let word_list: Vec<&str> = text.split(" ").collect();
-
-            let mut run_width: f32 = 0.;
-            let mut run_height: f32 = 0.;
-            let mut height_offset = 0.;
-            let mut run_y_offset = 0.;
-            let mut run_str: String = String::new();
-
-            for word in word_list {
-                let word_size = get_measurement(
-                    &mut self.measure_text_cache,
-                    &self.measure_text_fn,
-                    word,
-                    font_id,
-                    font_size,
-                );
-
-                // edge case: a single word is larger than the container's width
-                // equality here can happen as 0. is assigned as base value.
-                if run_width == 0. && word_size.width > element.dimensions.width {
-                    let mut text_element = create_text_element(element.id, word);
-                    text_element.position.y = height_offset;
-                    height_offset += word_size.height;
-                    text_lines.push(Rc::new(text_element));
-                }
-
-                // if adding this word cause the current run to overflow
-                if run_width + word_size.width + space_measurement.width > element.dimensions.width
-                {
-                    let mut text_element = create_text_element(element.id, &run_str);
-                    text_element.position.y = height_offset + run_y_offset;
-                    height_offset += run_height;
-                    run_str.clear();
-                    run_width = 0.;
-                    run_height = 0.;
-                    run_y_offset = 0.;
-                    text_lines.push(Rc::new(text_element));
-                }
+            // the first line is always kept, so there is something to put the ellipsis on.
+            let visible_lines = text_config
+                .text_lines
+                .iter()
+                .filter(|line| line.y + line.height <= element_dimensions.height + EPSILON)
+                .count()
+                .max(1);
 
-                if run_width != 0. {
-                    run_width += space_measurement.width;
-                    run_str += " ";
-                }
-                run_height = run_height.max(word_size.height);
-                run_y_offset = run_y_offset.max(word_size.y_offset);
-                run_width += word_size.width;
-                run_str += word;
+            if visible_lines >= text_config.text_lines.len() {
+                continue;
             }
 
-            // push the remaining texts
-            let mut text_element = create_text_element(element.id, &run_str);
-            text_element.position.y = height_offset + run_y_offset;
-            height_offset += run_height;
-            text_lines.push(Rc::new(text_element));
-            element.dimensions.height = height_offset;
-
-            if let TypeConfig::Text(text_config) = &mut element.element_config {
-                text_config.text_lines = text_lines;
-            }
+            let spans = &text_config.spans;
+            let letter_spacing = text_config.letter_spacing;
+
+            text::truncate_lines(
+                &mut text_config.text_lines,
+                visible_lines,
+                element_dimensions.width,
+                &text_config.text_overflow,
+                |text, span| {
+                    get_measurement(
+                        &mut self.measure_text_cache,
+                        &self.measure_text_fn,
+                        text,
+                        spans[span].font_id,
+                        spans[span].font_size,
+                        letter_spacing,
+                    )
+                },
+            );
         }
     }
 
@@ -1033,36 +1524,109 @@ impl LayoutContext {
         // Step 3: Grow Width
         self.grow_sizing(true);
 
-        // Step 4: Wrap Text
+        // Step 4: Aspect Ratio Height
+        self.aspect_ratio_sizing();
+
+        // Step 5: Wrap Text
         self.wrap_text();
 
-        // Step 5: Fit Sizing Height
+        // Step 6: Fit Sizing Height
         self.fit_sizing(false);
 
-        // Step 6: Percentage Height
+        // Step 7: Percentage Height
         self.percent_sizing(false);
 
-        // Step 7: Grow Height
+        // Step 8: Grow Height
         self.grow_sizing(false);
 
-        // Step 8: Positions
+        // Step 9: Truncate Text
+        self.truncate_text();
+
+        // Step 10: Positions
         self.position_element();
 
+        // Step 11: Floating Positions
+        self.position_floating_elements();
+
+        // focus moves only once every element has its bounds.
+        self.navigate_focus();
+
         let mut render_commands: Vec<RenderCommand> = Vec::new();
 
         // remove the implicit root element (TODO: think about exposing this root to public for use?)
-        self.element_tree_post_order.pop();
+        let root_element = self
+            .element_tree_post_order
+            .pop()
+            .expect("Root element must always be there");
+
+        // the map outlives the frame, so queries made while declaring the next one see this layout.
+        self.element_map.clear();
+        for element in &self.element_tree_post_order {
+            if let Some(id) = element.borrow().config_id() {
+                self.element_map.insert(id, element.clone());
+            }
+        }
+
+        self.scroll_text_inputs();
 
         /*
             A consequence to using the stack is that element at the same level in the tree
             will be drawn in reverse order of insertion. That is, if A and B is inserted
             in that order and at the same level, this will create render command for B
             before A.
+
+            In that order, the descendants of an element come right after it, so a clipping
+            element's scissor ends once as many elements as it has descendants were drawn.
+            Floating elements come after the rest of the layout, each on its own layer.
         */
 
-        for element in (self.element_tree_post_order).iter().rev() {
+        let mut draw_order: Vec<(ElementReference, usize)> = Vec::new();
+        for child in root_element.borrow().child_elements.iter().rev() {
+            push_draw_order(child, 0, &mut draw_order);
+        }
+
+        // layers of the floating elements the pointer goes through.
+        let mut passthrough_layers: Vec<usize> = Vec::new();
+        for (layer, floating_root) in self.floating_draw_order().iter().enumerate() {
+            if let TypeConfig::Rectangle(element_config) = &floating_root.borrow().element_config
+                && element_config
+                    .floating
+                    .is_some_and(|floating_config| !floating_config.capture_pointer)
+            {
+                passthrough_layers.push(layer + 1);
+            }
+
+            push_draw_order(floating_root, layer + 1, &mut draw_order);
+        }
+
+        // index of the element each open scissor ends before, and the area left visible.
+        let mut scissors: Vec<(usize, BoundingBox)> = Vec::new();
+        self.hit_boxes.clear();
+
+        for (index, (element, layer)) in draw_order.iter().enumerate() {
+            while scissors.last().is_some_and(|(end, _)| *end == index) {
+                scissors.pop();
+                render_commands.push(RenderCommand {
+                    position: Position::default(),
+                    render_data: RenderData::ScissorEnd,
+                });
+            }
+
             let element = element.borrow();
 
+            let visible_box = match scissors.last() {
+                Some((_, scissor_box)) => element.bounding_box().intersection(scissor_box),
+                None => element.bounding_box(),
+            };
+
+            if !passthrough_layers.contains(layer) {
+                self.hit_boxes.push(HitBox {
+                    id: element.config_id(),
+                    bounds: visible_box,
+                    layer: *layer,
+                });
+            }
+
             match &element.element_config {
                 TypeConfig::Rectangle(element_config) => {
                     render_commands.push(RenderCommand {
@@ -1072,22 +1636,106 @@ impl LayoutContext {
                             color: element_config.color,
                         }),
                     });
+
+                    if element_config.clip.is_some() {
+                        render_commands.push(RenderCommand {
+                            position: element.position,
+                            render_data: RenderData::ScissorStart(ScissorRenderData {
+                                dimensions: element.dimensions,
+                            }),
+                        });
+                        scissors.push((index + 1 + element.descendant_count(), visible_box));
+                    }
                 }
                 TypeConfig::Text(element_config) => {
-                    for text_element in &element_config.text_lines {
+                    let y_offset = element_config.vertical_offset(element.dimensions.height);
+                    let spans = &element_config.spans;
+                    let letter_spacing = element_config.letter_spacing;
+
+                    let mut measure = |word: &str, span: usize| {
+                        get_measurement(
+                            &mut self.measure_text_cache,
+                            &self.measure_text_fn,
+                            word,
+                            spans[span].font_id,
+                            spans[span].font_size,
+                            letter_spacing,
+                        )
+                    };
+
+                    for line in &element_config.text_lines {
+                        // the highlight goes first so the text is drawn over it.
+                        if let Some(selection) = element_config.selection {
+                            let selected = selection.start.min(selection.end)
+                                ..selection.start.max(selection.end);
+
+                            for (left, right) in
+                                text::selection_extents(line, selected, &mut measure)
+                            {
+                                render_commands.push(RenderCommand {
+                                    position: Position {
+                                        x: element.position.x + left,
+                                        y: element.position.y + y_offset + line.y,
+                                    },
+                                    render_data: RenderData::Rectangle(RectangleRenderData {
+                                        dimenions: Dimensions {
+                                            width: right - left,
+                                            height: line.height,
+                                        },
+                                        color: selection.color,
+                                    }),
+                                });
+                            }
+                        }
+
+                        for fragment in &line.fragments {
+                            let span = &element_config.spans[fragment.span];
+
+                            render_commands.push(RenderCommand {
+                                position: Position {
+                                    x: element.position.x + fragment.x,
+                                    y: element.position.y + y_offset + line.y,
+                                },
+                                render_data: RenderData::Text(TextRenderData {
+                                    font_id: span.font_id,
+                                    baseline: element.position.y
+                                        + y_offset
+                                        + line.y
+                                        + line.baseline,
+                                    text: fragment.text.clone(),
+                                    rtl: fragment.rtl,
+                                    font_size: span.font_size,
+                                    font_color: span.font_color,
+                                    letter_spacing: element_config.letter_spacing,
+                                }),
+                            });
+                        }
+                    }
+
+                    // the caret goes last, over the text.
+                    if let Some(caret) = element_config.caret
+                        && let Some(line) = element_config
+                            .text_lines
+                            .get(text::caret_line(&element_config.text_lines, caret.index))
+                    {
+                        let x = text::caret_x(
+                            line,
+                            caret.index,
+                            element.dimensions.width,
+                            &mut measure,
+                        );
+
                         render_commands.push(RenderCommand {
                             position: Position {
-                                x: element.position.x,
-                                y: element.position.y + text_element.position.y,
+                                x: element.position.x + x,
+                                y: element.position.y + y_offset + line.y,
                             },
-                            render_data: RenderData::Text(TextRenderData {
-                                font_id: element_config.font_id,
-                                text: match &text_element.element_config {
-                                    TypeConfig::Text(text_config) => text_config.text.clone(),
-                                    _ => panic!("This has to be text type config"),
+                            render_data: RenderData::Rectangle(RectangleRenderData {
+                                dimenions: Dimensions {
+                                    width: caret.width,
+                                    height: line.height,
                                 },
-                                font_size: element_config.font_size,
-                                font_color: element_config.font_color,
+                                color: caret.color,
                             }),
                         });
                     }
@@ -1095,29 +1743,353 @@ impl LayoutContext {
             }
         }
 
+        for _ in scissors {
+            render_commands.push(RenderCommand {
+                position: Position::default(),
+                render_data: RenderData::ScissorEnd,
+            });
+        }
+
+        // input is only ever handed to the frame it was pushed for.
+        self.input_events.clear();
+        self.pointer.scroll = Position::default();
+
+        if !self.pointer.down {
+            self.active = None;
+        }
+
         render_commands
     }
 
-    fn open_element(&mut self, element_config: Rc<ElementConfig>) {
-        self.element_stack.push_back(Element::new(
-            self.top_id,
-            TypeConfig::Rectangle(element_config),
-        ));
-        self.top_id += 1;
-    }
+    /**
+     * Place floating elements on what they are attached to, moving their children along.
+     * Floating elements declared inside others are closed first, so the outer ones are
+     * placed first.
+     */
+    fn position_floating_elements(&mut self) {
+        let root = BoundingBox {
+            x: 0.,
+            y: 0.,
+            width: self.root_dimensions.width,
+            height: self.root_dimensions.height,
+        };
+
+        for (floating_root, parent_id) in self.floating_roots.iter().rev() {
+            let floating_config = match &floating_root.borrow().element_config {
+                TypeConfig::Rectangle(element_config) => element_config.floating,
+                TypeConfig::Text(_) => None,
+            };
+            let Some(floating_config) = floating_config else {
+                continue;
+            };
 
-    fn close_element(&mut self) {
-        let mut current_element = self
-            .element_stack
-            .pop_back()
-            .expect("The element stack cannot be empty");
+            let target = match floating_config.attach_to {
+                FloatingAttach::Parent => self
+                    .element_tree_post_order
+                    .iter()
+                    .find(|element| element.borrow().id == *parent_id)
+                    .map(|element| element.borrow().bounding_box()),
+                FloatingAttach::Element(id) => self
+                    .element_tree_post_order
+                    .iter()
+                    .find(|element| element.borrow().config_id() == Some(id))
+                    .map(|element| element.borrow().bounding_box()),
+                FloatingAttach::Root => Some(root),
+                FloatingAttach::Point(position) => Some(BoundingBox {
+                    x: position.x,
+                    y: position.y,
+                    width: 0.,
+                    height: 0.,
+                }),
+            };
 
-        let mut parent_element = self
-            .element_stack
-            .pop_back()
-            .expect("Any element must have a parent element.");
+            // an element that is not in the layout leaves it where it is.
+            let Some(target) = target else {
+                continue;
+            };
 
-        // Configure constant values
+            let (current, dimensions) = {
+                let element = floating_root.borrow();
+                (element.position, element.dimensions)
+            };
+            let position = floating_position(&target, dimensions, &floating_config, &root);
+
+            shift_element(
+                floating_root,
+                position.x - current.x,
+                position.y - current.y,
+            );
+        }
+    }
+
+    /// Floating elements in the order they are drawn: by z index, then outer ones first.
+    fn floating_draw_order(&self) -> Vec<ElementReference> {
+        let mut floating_roots: Vec<(ElementReference, i16)> = Vec::new();
+
+        for (element, parent_id) in self.floating_roots.iter().rev() {
+            let z_index = match &element.borrow().element_config {
+                TypeConfig::Rectangle(element_config) => element_config
+                    .floating
+                    .map_or(0, |floating_config| floating_config.z_index),
+                TypeConfig::Text(_) => 0,
+            };
+
+            // outer floating elements come first, so theirs is already known.
+            let outer_z_index = floating_roots
+                .iter()
+                .filter(|(outer, _)| contains_element(&outer.borrow(), *parent_id))
+                .map(|(_, outer_z_index)| *outer_z_index)
+                .max();
+
+            floating_roots.push((
+                element.clone(),
+                z_index.max(outer_z_index.unwrap_or(z_index)),
+            ));
+        }
+
+        floating_roots.sort_by_key(|(_, z_index)| *z_index);
+
+        floating_roots
+            .into_iter()
+            .map(|(element, _)| element)
+            .collect()
+    }
+
+    /**
+     * Move the focus with the keys of the frame the focused element did not use:
+     * Tab and Shift-Tab cycle through the focusable elements, arrow keys move to the
+     * closest one in their direction.
+     */
+    fn navigate_focus(&mut self) {
+        let Some(root) = self.element_tree_post_order.last() else {
+            return;
+        };
+
+        // floating elements come after the rest of the layout.
+        let floating_roots = self.floating_draw_order();
+
+        // with a modal open, only what is drawn over the top one can take focus.
+        let mut focusables: Vec<Focusable> = Vec::new();
+        match self.modals.last() {
+            Some(modal) => {
+                let first_over_modal = floating_roots
+                    .iter()
+                    .position(|element| element.borrow().config_id() == Some(*modal))
+                    .unwrap_or(0);
+
+                for floating_root in &floating_roots[first_over_modal..] {
+                    collect_focusables(&floating_root.borrow(), &mut focusables);
+                }
+            }
+            None => {
+                collect_focusables(&root.borrow(), &mut focusables);
+                for floating_root in &floating_roots {
+                    collect_focusables(&floating_root.borrow(), &mut focusables);
+                }
+            }
+        }
+
+        for input in &self.input_events {
+            let InputEvent::Key { key, shift } = &input.event else {
+                continue;
+            };
+
+            if input.handled {
+                continue;
+            }
+
+            let target = match key {
+                Key::Tab => focus::tab_target(&focusables, self.focused, *shift),
+                Key::Left | Key::Right | Key::Up | Key::Down => {
+                    let current = self
+                        .focused
+                        .filter(|id| focusables.iter().any(|focusable| focusable.id == *id));
+
+                    // arrows start from the first element when nothing on screen has focus.
+                    match current {
+                        Some(current) => focus::spatial_target(&focusables, current, *key),
+                        None => focus::tab_target(&focusables, None, false),
+                    }
+                }
+                _ => continue,
+            };
+
+            if target.is_some() {
+                self.focused = target;
+            }
+        }
+    }
+
+    /**
+     * Keep the caret of single-line text inputs in view, scrolling their text horizontally.
+     * This runs once the layout is done, as only then is the caret's position known.
+     */
+    fn scroll_text_inputs(&mut self) {
+        for (id, state) in &mut self.text_inputs {
+            if state.multiline {
+                continue;
+            }
+
+            let (Some(container), Some(text_element)) = (
+                self.element_map.get(id),
+                self.element_map.get(&id.child(TEXT_INPUT_TEXT)),
+            ) else {
+                continue;
+            };
+
+            let container = container.borrow();
+            let mut text_element = text_element.borrow_mut();
+
+            let (TypeConfig::Rectangle(container_config), TypeConfig::Text(text_config)) =
+                (&container.element_config, &text_element.element_config)
+            else {
+                continue;
+            };
+
+            let spans = &text_config.spans;
+            let letter_spacing = text_config.letter_spacing;
+
+            let mut measure = |word: &str, span: usize| {
+                get_measurement(
+                    &mut self.measure_text_cache,
+                    &self.measure_text_fn,
+                    word,
+                    spans[span].font_id,
+                    spans[span].font_size,
+                    letter_spacing,
+                )
+            };
+
+            let lines = &text_config.text_lines;
+            let caret_x = lines
+                .get(text::caret_line(lines, state.cursor))
+                .map_or(0., |line| {
+                    text::caret_x(
+                        line,
+                        state.cursor,
+                        text_element.dimensions.width,
+                        &mut measure,
+                    )
+                });
+            let caret_width = text_config.caret.map_or(0., |caret| caret.width);
+
+            // positions from the start of the content box, as if the text was not scrolled.
+            let padding = container_config.padding;
+            let text_x =
+                text_element.position.x + state.scroll - (container.position.x + padding.left);
+            let caret_position = text_x + caret_x;
+            let content_width =
+                (text_x + text_element.dimensions.width).max(caret_position + caret_width);
+            let view_width = container.dimensions.width - padding.left - padding.right;
+
+            // text that got shorter scrolls back, but never further than its start.
+            let mut scroll = state.scroll.min(content_width - view_width).max(0.);
+            if caret_position + caret_width > scroll + view_width {
+                scroll = caret_position + caret_width - view_width;
+            }
+            if caret_position < scroll {
+                scroll = caret_position;
+            }
+
+            text_element.position.x -= scroll - state.scroll;
+            state.scroll = scroll;
+        }
+    }
+
+    /**
+     * Index in the text of the text element `id` closest to a point, such as the
+     * mouse position, after the last layout. `None` when no text element has that id.
+     */
+    pub fn text_index_at(&mut self, id: ElementId, position: Position) -> Option<usize> {
+        let element = self.element_map.get(&id)?.borrow();
+        let TypeConfig::Text(text_config) = &element.element_config else {
+            return None;
+        };
+
+        let y_offset = text_config.vertical_offset(element.dimensions.height);
+        let spans = &text_config.spans;
+        let letter_spacing = text_config.letter_spacing;
+
+        let mut measure = |word: &str, span: usize| {
+            get_measurement(
+                &mut self.measure_text_cache,
+                &self.measure_text_fn,
+                word,
+                spans[span].font_id,
+                spans[span].font_size,
+                letter_spacing,
+            )
+        };
+
+        Some(text::index_at(
+            &text_config.text_lines,
+            position.x - element.position.x,
+            position.y - element.position.y - y_offset,
+            &mut measure,
+        ))
+    }
+
+    /**
+     * Where the caret sits before the character at `index` in the text element `id`,
+     * after the last layout. The box has no width and is as tall as the line.
+     */
+    pub fn caret_box(&mut self, id: ElementId, index: usize) -> Option<BoundingBox> {
+        let element = self.element_map.get(&id)?.borrow();
+        let TypeConfig::Text(text_config) = &element.element_config else {
+            return None;
+        };
+
+        let y_offset = text_config.vertical_offset(element.dimensions.height);
+        let spans = &text_config.spans;
+        let letter_spacing = text_config.letter_spacing;
+
+        let mut measure = |word: &str, span: usize| {
+            get_measurement(
+                &mut self.measure_text_cache,
+                &self.measure_text_fn,
+                word,
+                spans[span].font_id,
+                spans[span].font_size,
+                letter_spacing,
+            )
+        };
+
+        let lines = &text_config.text_lines;
+        let line = lines.get(text::caret_line(lines, index))?;
+        let x = text::caret_x(line, index, element.dimensions.width, &mut measure);
+
+        Some(BoundingBox {
+            x: element.position.x + x,
+            y: element.position.y + y_offset + line.y,
+            width: 0.,
+            height: line.height,
+        })
+    }
+
+    fn open_element(&mut self, mut element_config: Rc<ElementConfig>) {
+        if self.mirrored {
+            element_config = Rc::new(element_config.mirrored(self.parent_layout_direction()));
+        }
+
+        self.element_stack.push_back(Element::new(
+            self.top_id,
+            TypeConfig::Rectangle(element_config),
+        ));
+        self.top_id += 1;
+    }
+
+    fn close_element(&mut self) {
+        let mut current_element = self
+            .element_stack
+            .pop_back()
+            .expect("The element stack cannot be empty");
+
+        let mut parent_element = self
+            .element_stack
+            .pop_back()
+            .expect("Any element must have a parent element.");
+
+        // Configure constant values
         {
             let width_config = match current_element.element_config {
                 TypeConfig::Rectangle(ref rect_conf) => rect_conf.width,
@@ -1126,7 +2098,7 @@ impl LayoutContext {
 
             match width_config {
                 DimensionConfig::Fixed(conf) => {
-                    current_element.dimensions.width = conf.size;
+                    current_element.dimensions.width = width_config.clamp(conf.size);
                 }
                 DimensionConfig::Grow(conf) => {
                     current_element.dimensions.width = conf.min_size;
@@ -1141,20 +2113,39 @@ impl LayoutContext {
 
             match height_config {
                 DimensionConfig::Fixed(conf) => {
-                    current_element.dimensions.height = conf.size;
+                    current_element.dimensions.height = height_config.clamp(conf.size);
                 }
                 DimensionConfig::Grow(conf) => {
                     current_element.dimensions.height = conf.min_size;
                 }
                 _ => {}
             };
+
+            // a fixed height is known right away, so the width can be treated as a constant too.
+            if current_element.sized_by_aspect_ratio(true)
+                && let TypeConfig::Rectangle(ref rect_conf) = current_element.element_config
+                && let Some(aspect_ratio) = rect_conf.aspect_ratio
+            {
+                current_element.dimensions.width = current_element.dimensions.height * aspect_ratio;
+            }
         }
 
+        let floating = matches!(
+            &current_element.element_config,
+            TypeConfig::Rectangle(rect_conf) if rect_conf.floating.is_some()
+        );
+
         let current_element = Rc::new(RefCell::new(current_element));
 
-        parent_element
-            .child_elements
-            .push(Rc::clone(&current_element));
+        // floating elements are laid out on their own, like roots.
+        if floating {
+            self.floating_roots
+                .push((Rc::clone(&current_element), parent_element.id));
+        } else {
+            parent_element
+                .child_elements
+                .push(Rc::clone(&current_element));
+        }
         self.element_tree_post_order.push(current_element);
         self.element_stack.push_back(parent_element);
     }
@@ -1170,14 +2161,40 @@ impl LayoutContext {
     }
 
     /**
-     * Fit Sizing will cause the text to collapse
-     * TODO make better docs
+     * Add a text element. With fit sizing, its width is the width of its longest paragraph,
+     * so it only wraps when clamped by a maximum size.
      */
     pub fn add_text(&mut self, text: &str, text_config: TextConfig) {
+        self.add_rich_text(
+            &[TextSpan {
+                text,
+                ..Default::default()
+            }],
+            text_config,
+        );
+    }
+
+    /**
+     * Add a text element made of differently styled spans, laid out as one text: lines wrap
+     * across span boundaries, and the words of a line share their baseline.
+     */
+    pub fn add_rich_text(&mut self, spans: &[TextSpan], text_config: TextConfig) {
         // text element cannot have children, so we implement custom logic instead of reusing.
 
         let mut element_starting_width = 0.;
 
+        let mut text_config = text_config;
+        if self.mirrored {
+            text_config.margin = text_config.margin.mirrored();
+            text_config.text_alignment = text_config.text_alignment.mirrored();
+
+            if !self.parent_layout_direction().is_horizontal() {
+                text_config.self_alignment = text_config
+                    .self_alignment
+                    .map(|alignment| alignment.mirrored());
+            }
+        }
+
         let mut parent_element = self
             .element_stack
             .pop_back()
@@ -1185,19 +2202,23 @@ impl LayoutContext {
 
         match text_config.width {
             DimensionConfig::Grow(_) => {
-                let text_dimension =
-                    (self.measure_text_fn)(text, text_config.font_id, text_config.font_size);
-                element_starting_width = text_dimension.width;
+                element_starting_width = self.natural_text_width(spans, &text_config);
+            }
+            DimensionConfig::Fit(_) => {
+                element_starting_width = text_config
+                    .width
+                    .clamp(self.natural_text_width(spans, &text_config));
             }
             DimensionConfig::Fixed(fixed_config) => {
-                element_starting_width = fixed_config.size;
+                element_starting_width = text_config.width.clamp(fixed_config.size);
             }
             _ => {}
         }
 
-        let text_config = InternalTextConfig::new_from(text, text_config);
+        let text_config = InternalTextConfig::new_from(spans, text_config);
 
-        let mut current_element = Element::new(self.top_id, TypeConfig::Text(text_config));
+        let mut current_element =
+            Element::new(self.top_id, TypeConfig::Text(Box::new(text_config)));
         current_element.dimensions.width = element_starting_width;
 
         let current_element = Rc::new(RefCell::new(current_element));
@@ -1208,23 +2229,766 @@ impl LayoutContext {
         self.element_tree_post_order.push(current_element);
         self.element_stack.push_back(parent_element);
     }
+
+    /// Queue input for the current frame. It is handed to the focused element as it is declared.
+    pub fn push_input(&mut self, event: InputEvent) {
+        self.input_events.push(PendingInput {
+            event,
+            handled: false,
+        });
+    }
+
+    pub fn set_focus(&mut self, id: Option<ElementId>) {
+        self.focused = id;
+    }
+
+    pub fn focused_element(&self) -> Option<ElementId> {
+        self.focused
+    }
+
+    /**
+     * Update the pointer (mouse or touch), once per frame before declaring the layout.
+     * Presses and releases are told apart from the state of the previous frame.
+     */
+    pub fn set_pointer_state(&mut self, position: Position, down: bool) {
+        self.pointer = PointerState {
+            position,
+            down,
+            pressed: down && !self.pointer.down,
+            released: !down && self.pointer.down,
+            secondary_pressed: false,
+            secondary_down: self.pointer.secondary_down,
+            scroll: self.pointer.scroll,
+        };
+    }
+
+    /// Update the secondary pointer button (usually the right mouse button), after the pointer.
+    pub fn set_secondary_pointer_state(&mut self, down: bool) {
+        self.pointer.secondary_pressed = down && !self.pointer.secondary_down;
+        self.pointer.secondary_down = down;
+    }
+
+    /// How far the wheel or touchpad scrolled since the previous frame, for the next layout only.
+    /// A positive `y` moves further down the content.
+    pub fn set_pointer_scroll(&mut self, delta: Position) {
+        self.pointer.scroll = delta;
+    }
+
+    /// Seconds since the previous frame, once per frame, for what waits on time such as tooltips.
+    pub fn set_frame_time(&mut self, seconds: f32) {
+        self.frame_time = seconds;
+    }
+
+    /**
+     * Whether the pointer is over the visible part of the element `id` in the last layout.
+     * Only the topmost layer under the pointer counts, so elements covered by a floating
+     * element are not.
+     */
+    pub fn pointer_over(&self, id: ElementId) -> bool {
+        let position = self.pointer.position;
+        let Some(top_layer) = self
+            .hit_boxes
+            .iter()
+            .filter(|hit_box| hit_box.bounds.contains(position))
+            .map(|hit_box| hit_box.layer)
+            .max()
+        else {
+            return false;
+        };
+
+        self.hit_boxes.iter().any(|hit_box| {
+            hit_box.layer == top_layer
+                && hit_box.id == Some(id)
+                && hit_box.bounds.contains(position)
+        })
+    }
+
+    /// Whether the pointer is over the element being declared, for use inside `add_element`'s closure.
+    pub fn hovered(&self) -> bool {
+        self.element_stack
+            .back()
+            .and_then(|element| element.config_id())
+            .is_some_and(|id| self.pointer_over(id))
+    }
+
+    pub fn is_focused(&self, id: ElementId) -> bool {
+        self.focused == Some(id)
+    }
+
+    /// Whether the element being declared has focus, for use inside `add_element`'s closure.
+    pub fn focused(&self) -> bool {
+        let Some(element) = self.element_stack.back() else {
+            return false;
+        };
+
+        element.config_id().is_some() && element.config_id() == self.focused
+    }
+
+    /**
+     * Add an editable text input. Its text is kept from one frame to the next under `id`.
+     * While it has focus, it applies the input pushed for the frame, and shows its caret
+     * and selection. Single-line inputs scroll horizontally to keep the caret in view.
+     */
+    pub fn add_text_input(&mut self, id: ElementId, config: TextInputConfig) -> TextInputResponse {
+        let mut state = self
+            .text_inputs
+            .remove(&id)
+            .unwrap_or_else(|| TextInputState::new(config.multiline));
+        state.multiline = config.multiline;
+
+        // pressing the input focuses it and places the caret there, dragging selects.
+        let text_id = id.child(TEXT_INPUT_TEXT);
+        if self.pointer.pressed && self.pointer_over(id) {
+            self.focused = Some(id);
+            self.active = Some(id);
+
+            if let Some(index) = self.text_index_at(text_id, self.pointer.position) {
+                state.move_to(index, false);
+            }
+        } else if self.pointer.down
+            && self.active == Some(id)
+            && let Some(index) = self.text_index_at(text_id, self.pointer.position)
+        {
+            state.move_to(index, true);
+        }
+
+        let focused = self.focused == Some(id);
+        let mut response = TextInputResponse::default();
+
+        if focused && !self.input_blocked() {
+            for index in 0..self.input_events.len() {
+                let handled = match self.input_events[index].event.clone() {
+                    InputEvent::Text(text) => {
+                        response.changed |= state.insert(&text);
+                        true
+                    }
+                    InputEvent::Key { key, shift } => match key {
+                        Key::Left | Key::Right => {
+                            state.move_horizontally(key == Key::Right, shift);
+                            true
+                        }
+                        Key::Home | Key::End => {
+                            state.move_to_paragraph_edge(key == Key::End, shift);
+                            true
+                        }
+                        Key::Up | Key::Down if state.multiline => {
+                            if let Some(index) =
+                                self.vertical_caret_target(id, state.cursor, key == Key::Down)
+                            {
+                                state.move_to(index, shift);
+                            }
+                            true
+                        }
+                        Key::Backspace => {
+                            response.changed |= state.delete_backward();
+                            true
+                        }
+                        Key::Delete => {
+                            response.changed |= state.delete_forward();
+                            true
+                        }
+                        Key::Enter if state.multiline => {
+                            response.changed |= state.insert("\n");
+                            true
+                        }
+                        Key::Enter => {
+                            response.submitted = true;
+                            true
+                        }
+                        // single-line inputs let vertical arrows move the focus.
+                        Key::Up | Key::Down | Key::Tab | Key::Escape => false,
+                    },
+                };
+
+                self.input_events[index].handled |= handled;
+            }
+        }
+
+        let text = state.text.clone();
+        let selection = state.selection();
+        let cursor = state.cursor;
+        let scroll = state.scroll;
+        self.text_inputs.insert(id, state);
+
+        let (text_width, align_y) = if config.multiline {
+            (DimensionConfig::grow(), VerticalAlignment::Top)
+        } else {
+            (DimensionConfig::fit(), VerticalAlignment::Center)
+        };
+
+        self.add_element(
+            ElementConfig::new(ElementConfig {
+                id: Some(id),
+                width: config.width,
+                height: config.height,
+                padding: config.padding,
+                margin: config.margin,
+                child_alignment: AlignmentConfig {
+                    align_y,
+                    ..Default::default()
+                },
+                clip: Some(ClipConfig {
+                    child_offset: Position { x: -scroll, y: 0. },
+                }),
+                focus: Some(FocusConfig::default()),
+                color: config.color,
+                ..Default::default()
+            }),
+            |ctx| {
+                ctx.add_text(
+                    &text,
+                    TextConfig {
+                        id: Some(text_id),
+                        width: text_width,
+                        font_id: config.font_id,
+                        font_size: config.font_size,
+                        font_color: config.font_color,
+                        break_word: config.multiline,
+                        white_space: WhiteSpace::Preserve,
+                        selection: (focused && !selection.is_empty()).then_some(TextSelection {
+                            start: selection.start,
+                            end: selection.end,
+                            color: config.selection_color,
+                        }),
+                        caret: focused.then_some(TextCaret {
+                            index: cursor,
+                            color: config.caret_color,
+                            width: config.caret_width,
+                        }),
+                        ..Default::default()
+                    },
+                );
+            },
+        );
+
+        response
+    }
+
+    /// Text of the text input `id`, `None` until it is first declared or set.
+    pub fn text_input_value(&self, id: ElementId) -> Option<&str> {
+        self.text_inputs.get(&id).map(|state| state.text.as_str())
+    }
+
+    /// Replace the text of the text input `id`, placing the caret at its end.
+    pub fn set_text_input_value(&mut self, id: ElementId, text: &str) {
+        self.text_inputs
+            .entry(id)
+            .or_insert_with(|| TextInputState::new(false))
+            .set_text(text);
+    }
+
+    /// Mark the presses of `key` the focused element has not used yet as used, and count them.
+    pub(crate) fn take_key(&mut self, key: Key) -> usize {
+        if self.input_blocked() {
+            return 0;
+        }
+
+        let mut count = 0;
+
+        for input in &mut self.input_events {
+            if let InputEvent::Key { key: pressed, .. } = input.event
+                && pressed == key
+                && !input.handled
+            {
+                input.handled = true;
+                count += 1;
+            }
+        }
+
+        count
+    }
+
+    /**
+     * Add a modal, if `open`: `children` are declared over a backdrop covering the whole
+     * root, which keeps the pointer from the rest of the layout. Modals declared later go
+     * over the ones before them, and only the top one takes keys, Escape closing it when its
+     * content has no use for it. What is declared before a modal that opens in the frame
+     * still gets that frame's keys.
+     */
+    pub fn add_modal<F: FnOnce(&mut LayoutContext)>(
+        &mut self,
+        id: ElementId,
+        open: &mut bool,
+        modal_config: ModalConfig,
+        children: F,
+    ) {
+        if !*open {
+            return;
+        }
+
+        let z_index = MODAL_Z_INDEX + self.modals.len() as i16;
+        self.modals.push(id);
+
+        self.add_element(
+            ElementConfig::new(ElementConfig {
+                id: Some(id),
+                width: DimensionConfig::fixed(self.root_dimensions.width),
+                height: DimensionConfig::fixed(self.root_dimensions.height),
+                child_alignment: modal_config.child_alignment,
+                color: modal_config.backdrop_color,
+                floating: Some(FloatingConfig {
+                    attach_to: FloatingAttach::Root,
+                    z_index,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            |ctx| {
+                children(ctx);
+
+                // the modal under it takes keys from the next frame on.
+                if ctx.take_key(Key::Escape) > 0 {
+                    *open = false;
+                    ctx.modals.retain(|modal| *modal != id);
+                }
+            },
+        );
+    }
+
+    /**
+     * Whether keys are kept from the element being declared by a modal over it: the top
+     * modal of the last layout, or the last one declared so far, which may have just opened.
+     */
+    fn input_blocked(&self) -> bool {
+        [self.top_modal, self.modals.last().copied()]
+            .into_iter()
+            .flatten()
+            .any(|modal| {
+                !self
+                    .element_stack
+                    .iter()
+                    .any(|element| element.config_id() == Some(modal))
+            })
+    }
+
+    /// Index on the line above or below the caret, closest to it, from the last layout.
+    fn vertical_caret_target(&mut self, id: ElementId, index: usize, down: bool) -> Option<usize> {
+        let text_id = id.child(TEXT_INPUT_TEXT);
+        let caret = self.caret_box(text_id, index)?;
+
+        let y = if down {
+            caret.y + caret.height * 1.5
+        } else {
+            caret.y - caret.height / 2.
+        };
+
+        self.text_index_at(text_id, Position { x: caret.x, y })
+    }
+
+    /**
+     * Width of the text without wrapping, that is, of its widest paragraph. Paragraphs are
+     * measured as they are laid out, with their white space collapsed and their tabs
+     * reaching the next stop.
+     */
+    pub(crate) fn natural_text_width(
+        &mut self,
+        spans: &[TextSpan],
+        text_config: &TextConfig,
+    ) -> f32 {
+        let text_config = InternalTextConfig::new_from(spans, text_config.clone());
+        let letter_spacing = text_config.letter_spacing;
+        let mirrored = self.mirrored;
+
+        let space_measurement = get_measurement(
+            &mut self.measure_text_cache,
+            &self.measure_text_fn,
+            " ",
+            text_config.font_id,
+            text_config.font_size,
+            letter_spacing,
+        );
+        let tab_stop = space_measurement.width * text_config.tab_size as f32;
+
+        let spans = &text_config.spans;
+        let mut measure = |word: &str, span: usize| {
+            get_measurement(
+                &mut self.measure_text_cache,
+                &self.measure_text_fn,
+                word,
+                spans[span].font_id,
+                spans[span].font_size,
+                letter_spacing,
+            )
+        };
+
+        text::paragraphs(&text_config.text, spans, text_config.white_space, mirrored)
+            .iter()
+            .flat_map(|paragraph| {
+                let tokens = text::tokenize(paragraph, &mut measure);
+                text::break_lines(&tokens, f32::INFINITY, tab_stop, space_measurement)
+            })
+            .map(|line| line.width)
+            .fold(0., f32::max)
+    }
 }
 
 impl InternalTextConfig {
-    pub fn new_from(text: &str, text_config: TextConfig) -> InternalTextConfig {
+    /// Offset of the lines from the top of the element, for the element's final height.
+    pub fn vertical_offset(&self, height: f32) -> f32 {
+        let content_height = self
+            .text_lines
+            .last()
+            .map_or(0., |line| line.y + line.height);
+
+        match self.vertical_alignment {
+            VerticalAlignment::Top | VerticalAlignment::Baseline => 0.,
+            VerticalAlignment::Center => (height - content_height) / 2.,
+            VerticalAlignment::Bottom => height - content_height,
+        }
+    }
+
+    pub fn new_from(spans: &[TextSpan], text_config: TextConfig) -> InternalTextConfig {
+        let mut text = String::new();
+        let mut internal_spans: Vec<InternalTextSpan> = Vec::with_capacity(spans.len().max(1));
+
+        for span in spans {
+            let start = text.len();
+            text.push_str(span.text);
+
+            internal_spans.push(InternalTextSpan {
+                range: start..text.len(),
+                font_id: span.font_id.unwrap_or(text_config.font_id),
+                font_size: span.font_size.unwrap_or(text_config.font_size),
+                font_color: span.font_color.unwrap_or(text_config.font_color),
+            });
+        }
+
+        if internal_spans.is_empty() {
+            internal_spans.push(InternalTextSpan {
+                range: 0..0,
+                font_id: text_config.font_id,
+                font_size: text_config.font_size,
+                font_color: text_config.font_color,
+            });
+        }
+
         InternalTextConfig {
+            id: text_config.id,
             width: text_config.width,
             height: text_config.height,
             font_id: text_config.font_id,
             break_word: text_config.break_word,
             text: Rc::from(text),
+            spans: internal_spans,
             font_size: text_config.font_size,
-            font_color: text_config.font_color,
+            text_alignment: text_config.text_alignment,
+            white_space: text_config.white_space,
+            paragraph_spacing: text_config.paragraph_spacing,
+            tab_size: text_config.tab_size,
+            overflow_wrap: text_config.overflow_wrap,
+            text_overflow: text_config.text_overflow,
+            max_lines: text_config.max_lines,
+            line_height: text_config.line_height,
+            letter_spacing: text_config.letter_spacing,
+            line_breaking: text_config.line_breaking,
+            vertical_alignment: text_config.vertical_alignment,
+            selection: text_config.selection,
+            caret: text_config.caret,
+            margin: text_config.margin,
+            self_alignment: text_config.self_alignment,
             text_lines: Vec::new(),
         }
     }
 }
 
+/// The element and its descendants in the order they are drawn, on the given layer.
+fn push_draw_order(
+    element: &ElementReference,
+    layer: usize,
+    draw_order: &mut Vec<(ElementReference, usize)>,
+) {
+    draw_order.push((element.clone(), layer));
+
+    for child in element.borrow().child_elements.iter().rev() {
+        push_draw_order(child, layer, draw_order);
+    }
+}
+
+/// Whether the element with the internal id `id` is `element` or one of its descendants.
+fn contains_element(element: &Element, id: u64) -> bool {
+    element.id == id
+        || element
+            .child_elements
+            .iter()
+            .any(|child| contains_element(&child.borrow(), id))
+}
+
+/// Move an element along with all its descendants.
+fn shift_element(element: &ElementReference, x: f32, y: f32) {
+    let mut element = element.borrow_mut();
+    element.position.x += x;
+    element.position.y += y;
+
+    for child in &element.child_elements {
+        shift_element(child, x, y);
+    }
+}
+
+/// Where a floating element of the given size goes once attached to `target`.
+fn floating_position(
+    target: &BoundingBox,
+    dimensions: Dimensions,
+    floating_config: &FloatingConfig,
+    root: &BoundingBox,
+) -> Position {
+    let place = |element_point: AttachPoint, parent_point: AttachPoint, offset: Position| {
+        let (parent_x, parent_y) = parent_point.fractions();
+        let (element_x, element_y) = element_point.fractions();
+
+        Position {
+            x: target.x + target.width * parent_x - dimensions.width * element_x + offset.x,
+            y: target.y + target.height * parent_y - dimensions.height * element_y + offset.y,
+        }
+    };
+
+    let mut position = place(
+        floating_config.element_point,
+        floating_config.parent_point,
+        floating_config.offset,
+    );
+
+    if !floating_config.fit_in_root {
+        return position;
+    }
+
+    let flipped = place(
+        floating_config.element_point.flipped(),
+        floating_config.parent_point.flipped(),
+        Position {
+            x: -floating_config.offset.x,
+            y: -floating_config.offset.y,
+        },
+    );
+
+    let fits = |start: f32, size: f32, root_start: f32, root_size: f32| {
+        start >= root_start && start + size <= root_start + root_size
+    };
+
+    if !fits(position.x, dimensions.width, root.x, root.width)
+        && fits(flipped.x, dimensions.width, root.x, root.width)
+    {
+        position.x = flipped.x;
+    }
+    if !fits(position.y, dimensions.height, root.y, root.height)
+        && fits(flipped.y, dimensions.height, root.y, root.height)
+    {
+        position.y = flipped.y;
+    }
+
+    // what does not fit either way is pushed back in, starting from the left and top.
+    position.x = position
+        .x
+        .min(root.x + root.width - dimensions.width)
+        .max(root.x);
+    position.y = position
+        .y
+        .min(root.y + root.height - dimensions.height)
+        .max(root.y);
+
+    position
+}
+
+/// The focusable elements under `element`, in the order they were declared.
+fn collect_focusables(element: &Element, focusables: &mut Vec<Focusable>) {
+    if let TypeConfig::Rectangle(element_config) = &element.element_config
+        && let (Some(id), Some(focus_config)) = (element_config.id, element_config.focus)
+    {
+        focusables.push(Focusable {
+            id,
+            tab_index: focus_config.tab_index,
+            bounds: element.bounding_box(),
+        });
+    }
+
+    for child in &element.child_elements {
+        collect_focusables(&child.borrow(), focusables);
+    }
+}
+
+/**
+ * How far the children of a row aligned on their baseline reach above and below it,
+ * margins included. Children with their own alignment are left out.
+ */
+fn baseline_extents(children: &[ElementReference]) -> (f32, f32) {
+    let mut above: f32 = 0.;
+    let mut below: f32 = 0.;
+
+    for child in children {
+        let child = child.borrow();
+
+        if child.self_alignment().is_some() {
+            continue;
+        }
+
+        let margin = child.margin();
+        let baseline = child.baseline();
+
+        above = above.max(margin.top + baseline);
+        below = below.max(child.dimensions.height - baseline + margin.bottom);
+    }
+
+    (above, below)
+}
+
+/**
+ * Resolve the size of a percent child along one axis.
+ *
+ * Along the layout direction, the parent's padding is charged to the first and last
+ * child, and each gap is split between its two neighbours, so percentages adding up
+ * to 1 fill the parent exactly. Against the layout direction, each child is charged
+ * both paddings. Either way, the child's own margins come out of its share.
+ */
+fn percent_size(
+    parent: &Element,
+    parent_config: &ElementConfig,
+    child: &Element,
+    child_index: usize,
+    percent: f32,
+    x_axis: bool,
+) -> f32 {
+    let child_count = parent.child_elements.len();
+    let layout_direction = parent_config.child_layout_direction;
+    let padding = parent_config.padding;
+    let margin = child.margin();
+
+    let (mut size, leading_padding, trailing_padding, margins) = if x_axis {
+        (
+            parent.dimensions.width * percent,
+            padding.left,
+            padding.right,
+            margin.left + margin.right,
+        )
+    } else {
+        (
+            parent.dimensions.height * percent,
+            padding.top,
+            padding.bottom,
+            margin.top + margin.bottom,
+        )
+    };
+
+    if layout_direction.is_horizontal() == x_axis {
+        // the first declared child sits at the far end when the direction is reversed.
+        let (leading_padding, trailing_padding) = if layout_direction.is_reversed() {
+            (trailing_padding, leading_padding)
+        } else {
+            (leading_padding, trailing_padding)
+        };
+
+        if child_index == 0 {
+            size -= leading_padding;
+        }
+
+        if child_index == child_count - 1 {
+            size -= trailing_padding;
+        }
+
+        if child_count > 1 {
+            if child_index == 0 || child_index == child_count - 1 {
+                size -= parent_config.child_gap / 2.;
+            } else {
+                size -= parent_config.child_gap;
+            }
+        }
+    } else {
+        size -= leading_padding + trailing_padding;
+    }
+
+    let size_config = if x_axis {
+        child.width_config()
+    } else {
+        child.height_config()
+    };
+
+    size_config.clamp(size - margins)
+}
+
+/**
+ * Share the remaining space of a parent between its grow children along the layout direction.
+ *
+ * The smallest children are grown first until they catch up with the next smallest, so
+ * children end up the same size whenever their constraints allow it. Children that hit
+ * their max size leave the pool and the rest keep sharing what's left.
+ */
+fn distribute_grow(mut grow_children: Vec<ElementReference>, mut remaining: f32, x_axis: bool) {
+    const EPSILON: f32 = 0.01;
+
+    let size_of = |element: &ElementReference| -> f32 {
+        let element = element.borrow();
+        if x_axis {
+            element.dimensions.width
+        } else {
+            element.dimensions.height
+        }
+    };
+
+    let max_size_of = |element: &ElementReference| -> f32 {
+        let element = element.borrow();
+        if x_axis {
+            element.width_config().max_size()
+        } else {
+            element.height_config().max_size()
+        }
+    };
+
+    while remaining > EPSILON && !grow_children.is_empty() {
+        // the children to grow this round are the smallest ones
+        let mut smallest = size_of(&grow_children[0]);
+        let mut next = f32::INFINITY;
+
+        for child in &grow_children {
+            let size = size_of(child);
+
+            if size < smallest {
+                next = smallest;
+                smallest = size;
+            } else if (size - smallest).abs() > EPSILON && size < next {
+                next = size;
+            }
+        }
+
+        let targets: Vec<ElementReference> = grow_children
+            .iter()
+            .filter(|child| (size_of(child) - smallest).abs() <= EPSILON)
+            .cloned()
+            .collect();
+
+        // how much each target may grow: up to the next size, a fair share, and its max size
+        let mut step = (remaining / targets.len() as f32).min(next - smallest);
+
+        for child in &targets {
+            let max_size = max_size_of(child);
+
+            if max_size != 0. {
+                step = step.min((max_size - size_of(child)).max(0.));
+            }
+        }
+
+        for child in &targets {
+            let mut element = child.borrow_mut();
+
+            if x_axis {
+                element.dimensions.width += step;
+            } else {
+                element.dimensions.height += step;
+            }
+        }
+
+        remaining -= step * targets.len() as f32;
+
+        // children that reached their max size cannot grow anymore
+        let before = grow_children.len();
+        grow_children.retain(|child| {
+            let max_size = max_size_of(child);
+            max_size == 0. || size_of(child) < max_size - EPSILON
+        });
+
+        if step <= EPSILON && before == grow_children.len() {
+            break;
+        }
+    }
+}
+
 fn construct_key(word: &str, font_id: u32, font_size: u16) -> String {
     font_id.to_string() + &font_size.to_string() + word
 }
@@ -1235,30 +2999,456 @@ fn get_measurement(
     text: &str,
     font_id: u32,
     font_size: u16,
+    letter_spacing: f32,
 ) -> TextMeasurement {
     let key = construct_key(text, font_id, font_size);
 
-    match measurement_cache.get(&key) {
+    let mut text_measurement = match measurement_cache.get(&key) {
         Some(val) => *val,
         None => {
             let text_measurement = (measure_text)(text, font_id, font_size);
             measurement_cache.insert(key, text_measurement);
             text_measurement
         }
+    };
+
+    // the cache holds the font's own measurement, spacing is added on top.
+    text_measurement.width += letter_spacing_width(text, letter_spacing);
+    text_measurement
+}
+
+/// Width added by letter spacing, which follows every grapheme (including the last one).
+fn letter_spacing_width(text: &str, letter_spacing: f32) -> f32 {
+    if letter_spacing == 0. {
+        return 0.;
     }
+
+    text.graphemes(true).count() as f32 * letter_spacing
 }
 
-fn create_text_element(element_id: u64, text: &str) -> Element {
-    let text_config = InternalTextConfig {
-        width: DimensionConfig::Fixed(FixedDimensionConfig { size: 0. }),
-        height: DimensionConfig::Fixed(FixedDimensionConfig { size: 0. }),
-        font_id: 0,
-        break_word: false,
-        text: Rc::from(text),
-        font_size: 0,
-        font_color: Color::default(),
-        text_lines: Vec::new(),
-    };
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+
+    /// Position and size of the rectangles drawn for what `declare` adds under the root.
+    fn rectangles(declare: impl FnOnce(&mut LayoutContext)) -> Vec<(Position, Dimensions)> {
+        let mut ctx = LayoutContext::create_context(400., 400.);
+        ctx.begin_layout();
+        declare(&mut ctx);
+
+        ctx.end_layout()
+            .iter()
+            .filter_map(|command| match &command.render_data {
+                RenderData::Rectangle(rectangle) => Some((command.position, rectangle.dimenions)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// A 200x200 parent centering `count` 40x40 children along `direction`. Returns their
+    /// positions, first to last along it.
+    fn centered_children(
+        direction: LayoutDirection,
+        padding: PaddingConfig,
+        count: usize,
+    ) -> Vec<Position> {
+        let rectangles = rectangles(|ctx| {
+            let parent = ElementConfig::new(ElementConfig {
+                width: DimensionConfig::fixed(200.),
+                height: DimensionConfig::fixed(200.),
+                padding,
+                child_gap: 10.,
+                child_alignment: AlignmentConfig::new(
+                    HorizontalAlignment::Center,
+                    VerticalAlignment::Center,
+                ),
+                child_layout_direction: direction,
+                ..Default::default()
+            });
+            ctx.add_element(parent, |ctx| {
+                for _ in 0..count {
+                    let child = ElementConfig::new(ElementConfig {
+                        width: DimensionConfig::fixed(40.),
+                        height: DimensionConfig::fixed(40.),
+                        ..Default::default()
+                    });
+                    ctx.add_element(child, |_| {});
+                }
+            });
+        });
+
+        let mut positions: Vec<Position> = rectangles
+            .into_iter()
+            .filter(|(_, dimensions)| dimensions.width == 40.)
+            .map(|(position, _)| position)
+            .collect();
+        positions.sort_by(|a, b| (a.x + a.y).total_cmp(&(b.x + b.y)));
+        positions
+    }
+
+    #[test]
+    fn centered_children_only_count_the_gaps_between_them() {
+        let positions =
+            centered_children(LayoutDirection::LeftToRight, PaddingConfig::no_padding(), 2);
+        assert_eq!((positions[0].x, positions[1].x), (55., 105.));
+
+        let positions =
+            centered_children(LayoutDirection::TopToBottom, PaddingConfig::no_padding(), 2);
+        assert_eq!((positions[0].y, positions[1].y), (55., 105.));
+    }
+
+    #[test]
+    fn centered_children_stay_inside_the_padding() {
+        let padding = PaddingConfig::individual_padding(20., 0., 0., 20.);
 
-    Element::new(element_id, TypeConfig::Text(text_config))
+        let positions = centered_children(LayoutDirection::LeftToRight, padding, 1);
+        assert_eq!(positions[0].x, 90.);
+
+        let positions = centered_children(LayoutDirection::TopToBottom, padding, 1);
+        assert_eq!(positions[0].y, 90.);
+    }
+
+    #[test]
+    fn percent_children_are_sized_after_an_empty_sibling() {
+        let rectangles = rectangles(|ctx| {
+            let sized = ElementConfig::new(ElementConfig {
+                width: DimensionConfig::fixed(200.),
+                height: DimensionConfig::fixed(50.),
+                ..Default::default()
+            });
+            ctx.add_element(sized, |ctx| {
+                let half = ElementConfig::new(ElementConfig {
+                    width: DimensionConfig::percent(0.5),
+                    height: DimensionConfig::fixed(20.),
+                    ..Default::default()
+                });
+                ctx.add_element(half, |_| {});
+            });
+
+            let empty = ElementConfig::new(ElementConfig {
+                width: DimensionConfig::fixed(10.),
+                height: DimensionConfig::fixed(10.),
+                ..Default::default()
+            });
+            ctx.add_element(empty, |_| {});
+        });
+
+        let half = rectangles
+            .iter()
+            .find(|(_, dimensions)| dimensions.height == 20.);
+        assert_eq!(half.unwrap().1.width, 100.);
+    }
+
+    #[test]
+    fn percent_height_follows_a_fixed_height_in_a_grow_width_parent() {
+        let rectangles = rectangles(|ctx| {
+            let parent = ElementConfig::new(ElementConfig {
+                width: DimensionConfig::grow(),
+                height: DimensionConfig::fixed(100.),
+                ..Default::default()
+            });
+            ctx.add_element(parent, |ctx| {
+                let half = ElementConfig::new(ElementConfig {
+                    width: DimensionConfig::fixed(10.),
+                    height: DimensionConfig::percent(0.5),
+                    ..Default::default()
+                });
+                ctx.add_element(half, |_| {});
+            });
+        });
+
+        let half = rectangles
+            .iter()
+            .find(|(_, dimensions)| dimensions.width == 10.);
+        assert_eq!(half.unwrap().1.height, 50.);
+    }
+
+    /// Every character is 8 wide, with the baseline at three quarters of the font size.
+    fn measure(text: &str, _font_id: u32, font_size: u16) -> TextMeasurement {
+        TextMeasurement {
+            width: text.chars().count() as f32 * 8.,
+            ascent: font_size as f32 * 0.75,
+            descent: font_size as f32 * 0.25,
+            x_offset: 0.,
+        }
+    }
+
+    /// The strings drawn for a single text element under the root.
+    fn drawn_text(text: &str, text_config: TextConfig) -> Vec<String> {
+        drawn_runs(text, text_config, false)
+            .into_iter()
+            .map(|(_, text, _)| text)
+            .collect()
+    }
+
+    /// Position, string and direction of the runs drawn for a single text element under the root.
+    fn drawn_runs(text: &str, text_config: TextConfig, mirrored: bool) -> Vec<(f32, String, bool)> {
+        let mut ctx = LayoutContext::create_context(400., 400.);
+        ctx.set_measurement_fn(measure);
+        ctx.set_mirrored(mirrored);
+        ctx.begin_layout();
+        ctx.add_text(text, text_config);
+
+        ctx.end_layout()
+            .iter()
+            .filter_map(|command| match &command.render_data {
+                RenderData::Text(text) => {
+                    Some((command.position.x, text.text.to_string(), text.rtl))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Size of the first rectangle drawn for a single element under the root.
+    fn element_dimensions(element_config: ElementConfig) -> Dimensions {
+        let mut ctx = LayoutContext::create_context(400., 400.);
+        ctx.begin_layout();
+        ctx.add_element(ElementConfig::new(element_config), |_| {});
+
+        match &ctx.end_layout()[0].render_data {
+            RenderData::Rectangle(rectangle) => rectangle.dimenions,
+            _ => panic!("expected a rectangle"),
+        }
+    }
+
+    #[test]
+    fn aspect_ratio_drives_height_from_width() {
+        let dimensions = element_dimensions(ElementConfig {
+            width: DimensionConfig::fixed(100.),
+            aspect_ratio: Some(2.),
+            ..Default::default()
+        });
+
+        assert_eq!(dimensions.height, 50.);
+    }
+
+    #[test]
+    fn invalid_aspect_ratio_is_ignored() {
+        for aspect_ratio in [0., -1., f32::NAN, f32::INFINITY] {
+            let dimensions = element_dimensions(ElementConfig {
+                width: DimensionConfig::fixed(100.),
+                height: DimensionConfig::fixed(30.),
+                aspect_ratio: Some(aspect_ratio),
+                ..Default::default()
+            });
+            assert_eq!((dimensions.width, dimensions.height), (100., 30.));
+
+            let dimensions = element_dimensions(ElementConfig {
+                width: DimensionConfig::fixed(100.),
+                aspect_ratio: Some(aspect_ratio),
+                ..Default::default()
+            });
+            assert_eq!(dimensions.height, 0.);
+        }
+    }
+
+    #[test]
+    fn ellipsis_can_be_built_at_runtime() {
+        let ellipsis = ["…"; 2].concat();
+        let lines = drawn_text(
+            "aaaa bbbb cccc",
+            TextConfig {
+                width: DimensionConfig::fixed(80.),
+                max_lines: 1,
+                text_overflow: TextOverflow::Ellipsis(Cow::Owned(ellipsis)),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(lines.concat(), "aaaa bbb……");
+    }
+
+    #[test]
+    fn right_to_left_runs_stay_in_logical_order() {
+        let runs = drawn_runs("abc אבג def", TextConfig::default(), false);
+
+        assert_eq!(
+            runs,
+            [
+                (0., "abc ".to_string(), false),
+                (32., "אבג".to_string(), true),
+                (56., " def".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn paragraph_direction_comes_from_its_text() {
+        let text_config = TextConfig {
+            width: DimensionConfig::fixed(80.),
+            ..Default::default()
+        };
+
+        // the element is on the right of the root, a mirrored layout only decides for text
+        // without a strong direction.
+        assert_eq!(
+            drawn_runs("abc", text_config.clone(), true),
+            [(320., "abc".to_string(), false)]
+        );
+        assert_eq!(
+            drawn_runs("123", text_config.clone(), true),
+            [(376., "123".to_string(), false)]
+        );
+        assert_eq!(
+            drawn_runs("אבג", text_config, false),
+            [(56., "אבג".to_string(), true)]
+        );
+    }
+
+    #[test]
+    fn tabs_take_the_paragraph_direction() {
+        let text_config = TextConfig {
+            white_space: WhiteSpace::Preserve,
+            ..Default::default()
+        };
+        let runs = drawn_runs("a אבג\tדהו", text_config, false);
+        let rtl_runs: Vec<&str> = runs
+            .iter()
+            .filter(|(_, _, rtl)| *rtl)
+            .map(|(_, text, _)| text.as_str())
+            .collect();
+
+        assert_eq!(rtl_runs, ["אבג", "דהו"]);
+    }
+
+    #[test]
+    fn fit_text_is_as_wide_as_its_laid_out_paragraphs() {
+        // the tab reaches its stop past "ab", which the fit width leaves room for.
+        let text_config = TextConfig {
+            width: DimensionConfig::fit(),
+            white_space: WhiteSpace::Preserve,
+            ..Default::default()
+        };
+        let runs: Vec<(f32, String)> = drawn_runs("ab\tcd", text_config, false)
+            .into_iter()
+            .map(|(x, text, _)| (x, text))
+            .collect();
+        assert_eq!(runs, [(0., "ab".to_string()), (32., "cd".to_string())]);
+
+        // collapsed white space does not widen the element past "ab cd".
+        let text_config = TextConfig {
+            width: DimensionConfig::fit(),
+            text_alignment: TextAlignment::Right,
+            ..Default::default()
+        };
+        assert_eq!(drawn_runs("ab     cd", text_config, false)[0].0, 0.);
+    }
+
+    #[test]
+    fn optimal_line_breaking_evens_out_lines() {
+        let text_config = TextConfig {
+            width: DimensionConfig::fixed(48.),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            drawn_text("aaa bb cc ddddd", text_config.clone()),
+            ["aaa bb", "cc", "ddddd"]
+        );
+        assert_eq!(
+            drawn_text(
+                "aaa bb cc ddddd",
+                TextConfig {
+                    line_breaking: LineBreaking::Optimal,
+                    ..text_config
+                }
+            ),
+            ["aaa", "bb cc", "ddddd"]
+        );
+    }
+
+    #[test]
+    fn line_break_cache_forgets_paragraphs_no_longer_laid_out() {
+        let text_config = TextConfig {
+            width: DimensionConfig::fixed(48.),
+            line_breaking: LineBreaking::Optimal,
+            ..Default::default()
+        };
+
+        let mut ctx = LayoutContext::create_context(400., 400.);
+        ctx.set_measurement_fn(measure);
+
+        for text in ["aaa bb cc ddddd", "aaa bb cc ddddd", "ee ff"] {
+            ctx.begin_layout();
+            ctx.add_text(text, text_config.clone());
+            ctx.end_layout();
+        }
+        assert_eq!(ctx.line_break_cache.len(), 2);
+
+        ctx.begin_layout();
+        assert_eq!(ctx.line_break_cache.len(), 1);
+    }
+
+    /// A text element with an id whose text has a collapsed run of spaces before "cd".
+    fn collapsed_text_context() -> (LayoutContext, ElementId) {
+        let id = ElementId(1);
+        let mut ctx = LayoutContext::create_context(400., 400.);
+        ctx.set_measurement_fn(measure);
+        ctx.begin_layout();
+        ctx.add_text(
+            "ab     cd",
+            TextConfig {
+                id: Some(id),
+                ..Default::default()
+            },
+        );
+        ctx.end_layout();
+
+        (ctx, id)
+    }
+
+    #[test]
+    fn caret_skips_collapsed_white_space() {
+        let (mut ctx, id) = collapsed_text_context();
+
+        // "ab cd" is drawn, the caret before "c" is after three characters.
+        let caret = ctx.caret_box(id, 7).unwrap();
+        assert_eq!(caret.x, 24.);
+
+        let caret = ctx.caret_box(id, 9).unwrap();
+        assert_eq!(caret.x, 40.);
+    }
+
+    #[test]
+    fn index_at_skips_collapsed_white_space() {
+        let (mut ctx, id) = collapsed_text_context();
+
+        assert_eq!(ctx.text_index_at(id, Position { x: 31., y: 4. }), Some(8));
+        assert_eq!(ctx.text_index_at(id, Position { x: 23., y: 4. }), Some(7));
+    }
+
+    #[test]
+    fn selection_skips_collapsed_white_space() {
+        let selection = TextSelection {
+            start: 7,
+            end: 9,
+            color: Color::default(),
+        };
+        let mut ctx = LayoutContext::create_context(400., 400.);
+        ctx.set_measurement_fn(measure);
+        ctx.begin_layout();
+        ctx.add_text(
+            "ab     cd",
+            TextConfig {
+                selection: Some(selection),
+                ..Default::default()
+            },
+        );
+
+        let highlights: Vec<(f32, f32)> = ctx
+            .end_layout()
+            .iter()
+            .filter_map(|command| match &command.render_data {
+                RenderData::Rectangle(rectangle) => {
+                    Some((command.position.x, rectangle.dimenions.width))
+                }
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(highlights, [(24., 16.)]);
+    }
 }
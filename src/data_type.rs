@@ -1,9 +1,13 @@
 use std::{
+    borrow::Cow,
     cell::RefCell,
     collections::{HashMap, VecDeque},
+    ops::Range,
     rc::Rc,
 };
 
+use crate::text::{LineBreakEntry, LineBreakKey};
+
 // TYPE DEFINITION
 
 pub(crate) type ElementReference = Rc<RefCell<Element>>;
@@ -28,21 +32,129 @@ pub struct LayoutContext {
     pub(crate) top_id: u64, // temporary, will be replaced with proper ID generation
     pub(crate) root_dimensions: Dimensions,
     pub(crate) element_tree_post_order: Vec<ElementReference>,
+    /// Floating elements, in the order they were closed, with the internal id of their parent.
+    pub(crate) floating_roots: Vec<(ElementReference, u64)>,
     pub(crate) measure_text_fn: Box<TextMeasureFunction>,
     pub(crate) measure_text_cache: HashMap<String, TextMeasurement>,
+    /// Paragraphs using optimal line breaking, broken into lines. Those that were not laid
+    /// out during a frame are dropped at the start of the next one.
+    pub(crate) line_break_cache: HashMap<LineBreakKey, LineBreakEntry>,
+    /// Elements with an id, from the last layout.
+    pub(crate) element_map: HashMap<ElementId, ElementReference>,
+    /// Input from the host for the current frame, handed to the focused element.
+    pub(crate) input_events: Vec<PendingInput>,
+    pub(crate) focused: Option<ElementId>,
+    pub(crate) pointer: PointerState,
+    /// Seconds since the previous frame, as told by the host.
+    pub(crate) frame_time: f32,
+    /// How long the pointer has been over the targets of tooltips, by target id.
+    pub(crate) hover_times: HashMap<ElementId, HoverTime>,
+    /// Visible part of the elements, in the order they were drawn in the last layout.
+    pub(crate) hit_boxes: Vec<HitBox>,
+    /// The element the pointer was pressed on, while it is held down.
+    pub(crate) active: Option<ElementId>,
+    /// Dropdowns and context menus that are open, by the id of their owner. Those that were
+    /// not declared during a frame are closed at the start of the next one.
+    pub(crate) open_menus: HashMap<ElementId, MenuState>,
+    /// Modals open in the current layout, from the bottom one to the top one.
+    pub(crate) modals: Vec<ElementId>,
+    /// The top modal of the last layout. Only what is declared inside it takes keys.
+    pub(crate) top_modal: Option<ElementId>,
+    pub(crate) text_inputs: HashMap<ElementId, TextInputState>,
+    /// Selected tab of each tab container, by the container's id. Those of containers that
+    /// were not declared during a frame are dropped at the start of the next one.
+    pub(crate) selected_tabs: HashMap<ElementId, TabSelection>,
+    /// Scroll and row heights of each virtual list, by the list's id. Those of lists that
+    /// were not declared during a frame are dropped at the start of the next one.
+    pub(crate) virtual_lists: HashMap<ElementId, VirtualListState>,
+    pub(crate) mirrored: bool,
 }
 
+/// Identifies an element from one frame to the next.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ElementId(pub u64);
+
 #[derive(Clone, Copy)]
 pub struct ElementConfig {
+    pub id: Option<ElementId>,
     pub width: DimensionConfig,
     pub height: DimensionConfig,
     pub padding: PaddingConfig,
+    pub margin: MarginConfig,
     pub child_gap: f32,
     pub child_alignment: AlignmentConfig,
     pub child_layout_direction: LayoutDirection,
+    pub self_alignment: Option<SelfAlignment>,
+    /// Width divided by height. A fixed height drives the width, otherwise the width drives the height.
+    /// A ratio that is not a positive number is ignored.
+    pub aspect_ratio: Option<f32>,
+    pub clip: Option<ClipConfig>,
+    /// Lets the element take keyboard focus. It needs an id.
+    pub focus: Option<FocusConfig>,
+    pub floating: Option<FloatingConfig>,
     pub color: Color,
 }
 
+/**
+ * Takes the element out of the layout flow of its parent, to be drawn over the rest of the
+ * layout attached to something else. Floating elements are sized like roots: `Grow` and
+ * `Percent` leave them at their minimum size.
+ */
+#[derive(Clone, Copy)]
+pub struct FloatingConfig {
+    pub attach_to: FloatingAttach,
+    /// The point of the floating element that is placed on `parent_point` of what it is attached to.
+    pub element_point: AttachPoint,
+    pub parent_point: AttachPoint,
+    pub offset: Position,
+    /// Floating elements with a higher index are drawn over the others. Those declared
+    /// inside another floating element are drawn at least at its index.
+    pub z_index: i16,
+    /// On each axis where the element would stick out of the root, it is attached on the other
+    /// side of its target if it fits there, and is then moved inside the root if it still does not.
+    pub fit_in_root: bool,
+    /// Whether the element takes the pointer from what is under it. Tooltips let it through.
+    pub capture_pointer: bool,
+}
+
+#[derive(Clone, Copy)]
+pub enum FloatingAttach {
+    /// The element it is declared in.
+    Parent,
+    /// Any element of the current layout.
+    Element(ElementId),
+    Root,
+    /// A point of the root, such as where the pointer was pressed.
+    Point(Position),
+}
+
+#[derive(Clone, Copy)]
+pub enum AttachPoint {
+    LeftTop,
+    LeftCenter,
+    LeftBottom,
+    CenterTop,
+    CenterCenter,
+    CenterBottom,
+    RightTop,
+    RightCenter,
+    RightBottom,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct FocusConfig {
+    /// Elements with a tab index come first when tabbing, by increasing index,
+    /// followed by the others in the order they are declared.
+    pub tab_index: Option<u32>,
+}
+
+/// Hide whatever the children draw outside of the element.
+#[derive(Clone, Copy, Default)]
+pub struct ClipConfig {
+    /// Moves all the children, such as by the opposite of a scroll position.
+    pub child_offset: Position,
+}
+
 #[derive(Clone)]
 pub struct RenderCommand {
     pub position: Position,
@@ -55,6 +167,9 @@ pub enum RenderData {
     Rectangle(RectangleRenderData),
     Image(ImageRenderData),
     Border(BorderRenderData),
+    /// Everything up to the matching `ScissorEnd` is only drawn inside this area. Scissors nest.
+    ScissorStart(ScissorRenderData),
+    ScissorEnd,
 
     // TODO: figure out whether it is possible to inject arbitrary data
     // Otherwise, allow user to pass an ID and detect that ID for each
@@ -62,12 +177,25 @@ pub enum RenderData {
     Custom,
 }
 
+/**
+ * The command's position is the top left of the line the text is on. Text is drawn with
+ * its baseline at `baseline`, which is the same for all the text of a line.
+ *
+ * The text is in logical order. A right-to-left run is meant to be shaped as such, which
+ * lays its glyphs out from the right and mirrors its brackets, within the same space.
+ */
 #[derive(Clone)]
 pub struct TextRenderData {
     pub font_id: u32,
+    /// Absolute vertical position of the baseline.
+    pub baseline: f32,
     pub text: Rc<str>,
+    /// The text is a right-to-left run.
+    pub rtl: bool,
     pub font_size: u16,
     pub font_color: Color,
+    /// Extra space to add after every character, already accounted for by the layout.
+    pub letter_spacing: f32,
 }
 
 #[derive(Clone, Copy)]
@@ -85,32 +213,290 @@ pub struct ImageRenderData {
 #[derive(Clone, Copy)]
 pub struct BorderRenderData {}
 
+#[derive(Clone, Copy)]
+pub struct ScissorRenderData {
+    pub dimensions: Dimensions,
+}
+
 #[derive(Clone)]
 pub(crate) enum TypeConfig {
     Rectangle(Rc<ElementConfig>),
-    Text(InternalTextConfig),
+    Text(Box<InternalTextConfig>),
 }
 
 #[derive(Clone)]
 pub(crate) struct InternalTextConfig {
+    pub id: Option<ElementId>,
     pub width: DimensionConfig,
     pub height: DimensionConfig,
     pub font_id: u32,
     pub break_word: bool,
     pub text: Rc<str>,
+    /// Covers the whole text in order, there is always at least one.
+    pub spans: Vec<InternalTextSpan>,
+    pub font_size: u16,
+    pub text_alignment: TextAlignment,
+    pub white_space: WhiteSpace,
+    pub paragraph_spacing: f32,
+    pub tab_size: u16,
+    pub overflow_wrap: OverflowWrap,
+    pub text_overflow: TextOverflow,
+    pub max_lines: usize,
+    pub line_height: LineHeight,
+    pub letter_spacing: f32,
+    pub line_breaking: LineBreaking,
+    pub vertical_alignment: VerticalAlignment,
+    pub selection: Option<TextSelection>,
+    pub caret: Option<TextCaret>,
+    pub margin: MarginConfig,
+    pub self_alignment: Option<SelfAlignment>,
+    pub text_lines: Vec<TextLayoutLine>,
+}
+
+/// The style of a part of a text element's text.
+#[derive(Clone)]
+pub(crate) struct InternalTextSpan {
+    pub range: Range<usize>,
+    pub font_id: u32,
     pub font_size: u16,
     pub font_color: Color,
-    pub text_lines: Vec<Rc<Element>>,
 }
 
-#[derive(Clone, Copy)]
+/// A wrapped line of a text element, positioned relative to the element.
+#[derive(Clone)]
+pub(crate) struct TextLayoutLine {
+    pub y: f32,
+    pub height: f32,
+    /// Offset of the baseline from the top of the line.
+    pub baseline: f32,
+    /// Alignment of the line, already swapped in right-to-left paragraphs.
+    pub alignment: TextAlignment,
+    pub rtl: bool,
+    /// Part of the element's text on the line, trailing white space excluded.
+    pub range: Range<usize>,
+    pub fragments: Vec<TextFragment>,
+}
+
+/// A part of a line drawn in one go, positioned relative to the element.
+#[derive(Clone)]
+pub(crate) struct TextFragment {
+    pub text: Rc<str>,
+    /// Index into the text element's spans.
+    pub span: usize,
+    /// Part of the element's text shown, without what the layout adds (hyphen, ellipsis).
+    pub range: Range<usize>,
+    /// Text of the fragment's paragraph as laid out, with its white space applied.
+    pub paragraph: Rc<str>,
+    /// Index in the element's text of each byte of `paragraph`, and of its end.
+    pub source: Rc<[usize]>,
+    /// Part of `paragraph` shown.
+    pub paragraph_range: Range<usize>,
+    /// The text is right-to-left, its start is on the right.
+    pub rtl: bool,
+    pub x: f32,
+    pub width: f32,
+}
+
+#[derive(Clone)]
 pub struct TextConfig {
+    pub id: Option<ElementId>,
     pub width: DimensionConfig,
     pub height: DimensionConfig,
     pub font_id: u32,
     pub break_word: bool,
     pub font_size: u16,
     pub font_color: Color,
+    pub text_alignment: TextAlignment,
+    pub white_space: WhiteSpace,
+    /// Extra space between paragraphs, that is, after every hard line break.
+    pub paragraph_spacing: f32,
+    /// Distance between tab stops, in spaces.
+    pub tab_size: u16,
+    pub overflow_wrap: OverflowWrap,
+    /// What happens to the lines that do not fit the element's height or `max_lines`.
+    pub text_overflow: TextOverflow,
+    /// Maximum number of lines shown, 0 means no limit.
+    pub max_lines: usize,
+    pub line_height: LineHeight,
+    /// Extra space added after every character.
+    pub letter_spacing: f32,
+    pub line_breaking: LineBreaking,
+    /// Where the lines sit when the element is taller than them, `Baseline` acts as `Top`.
+    pub vertical_alignment: VerticalAlignment,
+    pub selection: Option<TextSelection>,
+    pub caret: Option<TextCaret>,
+    pub margin: MarginConfig,
+    pub self_alignment: Option<SelfAlignment>,
+}
+
+/// A highlighted part of a text element, between two byte indices of its text (in any order).
+#[derive(Clone, Copy)]
+pub struct TextSelection {
+    pub start: usize,
+    pub end: usize,
+    pub color: Color,
+}
+
+/// A caret drawn over a text element, before the character at byte index `index` of its text.
+#[derive(Clone, Copy)]
+pub struct TextCaret {
+    pub index: usize,
+    pub color: Color,
+    pub width: f32,
+}
+
+/// Input forwarded by the host, applied by the focused element while it is declared.
+#[derive(Clone)]
+pub enum InputEvent {
+    /// Typed characters, or a string committed by an input method.
+    Text(String),
+    /// A key that edits or navigates rather than types. `shift` extends the selection,
+    /// or reverses `Tab`.
+    Key { key: Key, shift: bool },
+}
+
+/// The visible part of an element. Floating elements and their children are on the layers
+/// above the layout, one for each floating element in the order they are drawn.
+#[derive(Clone, Copy)]
+pub(crate) struct HitBox {
+    pub id: Option<ElementId>,
+    pub bounds: BoundingBox,
+    pub layer: usize,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct MenuState {
+    /// The option Enter picks.
+    pub highlighted: usize,
+    /// Where a context menu was opened.
+    pub anchor: Position,
+    /// Opened during the current frame, by the press that would otherwise close it.
+    pub opening: bool,
+    /// The menu was declared since the open menus were last pruned.
+    pub declared: bool,
+}
+
+#[derive(Clone, Copy, Default)]
+pub(crate) struct PointerState {
+    pub position: Position,
+    pub down: bool,
+    /// Went down since the previous frame.
+    pub pressed: bool,
+    /// Went up since the previous frame.
+    pub released: bool,
+    /// The secondary button (usually the right mouse button) went down since the previous frame.
+    pub secondary_pressed: bool,
+    pub secondary_down: bool,
+    /// How far the wheel or touchpad scrolled since the previous frame.
+    pub scroll: Position,
+}
+
+/// The scroll position of a virtual list and the heights of its rows, kept from one frame to the next.
+#[derive(Default)]
+pub(crate) struct VirtualListState {
+    /// How far the rows are scrolled up.
+    pub scroll: f32,
+    /// With estimated row heights, the height of each row: measured once it was declared,
+    /// estimated before.
+    pub heights: Vec<f32>,
+    /// Sums of `heights` over ranges of rows, as a Fenwick tree, so the rows at a scroll
+    /// position are found without adding up all the rows above them.
+    pub height_sums: Vec<f32>,
+    /// The rows declared in the last layout.
+    pub rows: Range<usize>,
+    /// The list was declared, or scrolled with `set_virtual_list_scroll`, since the lists
+    /// were last pruned.
+    pub declared: bool,
+}
+
+/// Input waiting for the end of the frame. What the focused element did not use moves the focus.
+#[derive(Clone)]
+pub(crate) struct PendingInput {
+    pub event: InputEvent,
+    pub handled: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Key {
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Backspace,
+    Delete,
+    Enter,
+    Tab,
+    Escape,
+}
+
+#[derive(Clone, Copy)]
+pub struct TextInputConfig {
+    pub width: DimensionConfig,
+    pub height: DimensionConfig,
+    pub padding: PaddingConfig,
+    pub margin: MarginConfig,
+    pub color: Color,
+    pub font_id: u32,
+    pub font_size: u16,
+    pub font_color: Color,
+    /// Multi-line inputs wrap their text and take `Enter` as a line break instead of a submission.
+    pub multiline: bool,
+    pub caret_color: Color,
+    pub caret_width: f32,
+    pub selection_color: Color,
+}
+
+/// What happened to a text input while it was declared.
+#[derive(Clone, Copy, Default)]
+pub struct TextInputResponse {
+    /// The text was edited.
+    pub changed: bool,
+    /// `Enter` was pressed in a single-line input.
+    pub submitted: bool,
+}
+
+/// The backdrop of a modal, covering the whole root, and where it places the modal's content.
+#[derive(Clone, Copy)]
+pub struct ModalConfig {
+    pub backdrop_color: Color,
+    pub child_alignment: AlignmentConfig,
+}
+
+/// The selected tab of a tab container, kept from one frame to the next.
+pub(crate) struct TabSelection {
+    pub index: usize,
+    /// The container was declared, or its tab set, since the selections were last pruned.
+    pub declared: bool,
+}
+
+/// How long the pointer has been over the target of a tooltip.
+pub(crate) struct HoverTime {
+    pub seconds: f32,
+    /// The tooltip was declared since the times were last pruned.
+    pub declared: bool,
+}
+
+/// The content of a text input, kept from one frame to the next.
+pub(crate) struct TextInputState {
+    pub text: String,
+    /// Byte index of the caret.
+    pub cursor: usize,
+    /// Where the selection started, equal to `cursor` when nothing is selected.
+    pub anchor: usize,
+    /// How far single-line text is scrolled to the left.
+    pub scroll: f32,
+    pub multiline: bool,
+}
+
+/// A part of a rich text element. The style it leaves unset comes from the element's `TextConfig`.
+#[derive(Clone, Copy, Default)]
+pub struct TextSpan<'a> {
+    pub text: &'a str,
+    pub font_id: Option<u32>,
+    pub font_size: Option<u16>,
+    pub font_color: Option<Color>,
 }
 
 #[derive(Clone, Copy)]
@@ -127,14 +513,21 @@ pub enum DimensionConfig {
     Percent(PercentDimenionConfig),
 }
 
+/*
+    Every sizing variant can be clamped. A max_size of 0 means there is no upper bound.
+*/
+
 #[derive(Clone, Copy)]
 pub struct FitSizingConfig {
     pub min_size: f32,
+    pub max_size: f32,
 }
 
 #[derive(Clone, Copy)]
 pub struct FixedDimensionConfig {
     pub size: f32,
+    pub min_size: f32,
+    pub max_size: f32,
 }
 
 #[derive(Clone, Copy)]
@@ -146,6 +539,8 @@ pub struct GrowDimensionConfig {
 #[derive(Clone, Copy)]
 pub struct PercentDimenionConfig {
     pub percent: f32,
+    pub min_size: f32,
+    pub max_size: f32,
 }
 
 #[derive(Clone, Copy)]
@@ -156,6 +551,15 @@ pub struct PaddingConfig {
     pub bottom: f32,
 }
 
+/// Space kept around the element itself, outside of its background.
+#[derive(Clone, Copy)]
+pub struct MarginConfig {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
 #[derive(Clone, Copy)]
 pub struct AlignmentConfig {
     pub align_x: HorizontalAlignment,
@@ -174,12 +578,103 @@ pub enum VerticalAlignment {
     Top,
     Center,
     Bottom,
+    /// Align the first baselines of the children of a row, acts as `Top` in a column.
+    /// Elements without text use their bottom edge as baseline.
+    Baseline,
+}
+
+/// Horizontal alignment of each wrapped line within the text element.
+///
+/// `Justify` stretches the spacing between words so every line but the last fills the element.
+/// `Left` and `Right` swap in right-to-left paragraphs, so they act as start and end.
+#[derive(Clone, Copy)]
+pub enum TextAlignment {
+    Left,
+    Center,
+    Right,
+    Justify,
+}
+
+/// How spaces and tabs inside the text are handled. Line breaks (`\n`) are always honored.
+///
+/// `Collapse` turns every run of spaces and tabs into a single space and trims each paragraph.
+/// `Preserve` keeps every space, and tabs advance to the next tab stop.
+#[derive(Clone, Copy)]
+pub enum WhiteSpace {
+    Collapse,
+    Preserve,
+}
+
+/// What happens to a word wider than the text element.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OverflowWrap {
+    /// The word overflows the element on its own line.
+    Normal,
+    /// The word is broken between graphemes.
+    BreakWord,
+    /// The word is broken between graphemes, with a hyphen at the end of each broken part.
+    Hyphenate,
+}
+
+/// How the lines of wrapping text are chosen.
+#[derive(Clone, Copy)]
+pub enum LineBreaking {
+    /// Fill each line as much as possible before starting the next one.
+    Greedy,
+    /// Choose the breaks of a paragraph all together, so its lines are as even as possible.
+    /// This costs more, so the result is cached until the text or the width changes.
+    Optimal,
+}
+
+/// Height of each line of a text element.
+#[derive(Clone, Copy)]
+pub enum LineHeight {
+    /// Each line is as tall as the tallest word on it.
+    Auto,
+    /// Every line is exactly this tall.
+    Absolute(f32),
+    /// Every line is this many times the font size.
+    Multiplier(f32),
+}
+
+/// How text that does not fit its element is cut.
+///
+/// Only whole lines are ever hidden. With `Ellipsis`, the last visible line ends with the
+/// given string when lines were hidden, and any line wider than the element is shortened
+/// so that it fits along with the string. The string can be built at runtime, such as
+/// from a translation.
+#[derive(Clone)]
+pub enum TextOverflow {
+    Visible,
+    Clip,
+    Ellipsis(Cow<'static, str>),
+}
+
+/// Overrides the parent's `child_alignment` on the cross axis for a single element.
+///
+/// `Stretch` fills the parent's cross axis (minus padding), regardless of the element's own sizing.
+#[derive(Clone, Copy)]
+pub enum SelfAlignment {
+    Start,
+    Center,
+    End,
+    Stretch,
 }
 
 #[derive(Clone, Copy)]
 pub enum LayoutDirection {
     LeftToRight,
     TopToBottom,
+    RightToLeft,
+    BottomToTop,
+}
+
+#[derive(Clone, Copy)]
+pub struct BoundingBox {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
 }
 
 #[derive(Clone, Copy)]
@@ -196,14 +691,18 @@ pub struct Color {
     pub a: u8,
 }
 
-#[derive(Clone, Copy)]
+/**
+ * Size of a run of text, relative to its baseline: the text reaches `ascent` above the
+ * baseline and `descent` below it, so the baseline sits `ascent` below the top.
+ */
+#[derive(Clone, Copy, Default)]
 pub struct TextMeasurement {
     pub width: f32,
-    pub height: f32,
+    pub ascent: f32,
+    pub descent: f32,
 
-    // offsets, automatically added to position during layout phase.
+    // offset, automatically added to position during layout phase.
     pub x_offset: f32,
-    pub y_offset: f32,
 }
 
 // DEFAULT VALUES
@@ -211,17 +710,123 @@ pub struct TextMeasurement {
 impl Default for ElementConfig {
     fn default() -> Self {
         ElementConfig {
+            id: None,
             width: DimensionConfig::fit(),
             height: DimensionConfig::fit(),
             padding: PaddingConfig::same_padding(0.),
+            margin: MarginConfig::no_margin(),
             child_gap: 0.,
             child_alignment: AlignmentConfig::default(),
             child_layout_direction: LayoutDirection::LeftToRight,
+            self_alignment: None,
+            aspect_ratio: None,
+            clip: None,
+            focus: None,
+            floating: None,
             color: Color::default(),
         }
     }
 }
 
+impl Default for FloatingConfig {
+    fn default() -> Self {
+        FloatingConfig {
+            attach_to: FloatingAttach::Parent,
+            element_point: AttachPoint::LeftTop,
+            parent_point: AttachPoint::LeftTop,
+            offset: Position::default(),
+            z_index: 0,
+            fit_in_root: false,
+            capture_pointer: true,
+        }
+    }
+}
+
+impl Default for ModalConfig {
+    fn default() -> Self {
+        ModalConfig {
+            backdrop_color: Color {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 128,
+            },
+            child_alignment: AlignmentConfig::new(
+                HorizontalAlignment::Center,
+                VerticalAlignment::Center,
+            ),
+        }
+    }
+}
+
+impl Default for TextInputConfig {
+    fn default() -> Self {
+        TextInputConfig {
+            width: DimensionConfig::grow(),
+            height: DimensionConfig::fit(),
+            padding: PaddingConfig::same_padding(0.),
+            margin: MarginConfig::no_margin(),
+            color: Color {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 0,
+            },
+            font_id: 0,
+            font_size: 16,
+            font_color: Color {
+                r: 20,
+                g: 20,
+                b: 20,
+                a: 255,
+            },
+            multiline: false,
+            caret_color: Color {
+                r: 20,
+                g: 20,
+                b: 20,
+                a: 255,
+            },
+            caret_width: 1.,
+            selection_color: Color {
+                r: 70,
+                g: 130,
+                b: 230,
+                a: 96,
+            },
+        }
+    }
+}
+
+impl Default for TextConfig {
+    fn default() -> Self {
+        TextConfig {
+            id: None,
+            width: DimensionConfig::grow(),
+            height: DimensionConfig::fit(),
+            font_id: 0,
+            break_word: true,
+            font_size: 16,
+            font_color: Color::default(),
+            text_alignment: TextAlignment::Left,
+            white_space: WhiteSpace::Collapse,
+            paragraph_spacing: 0.,
+            tab_size: 4,
+            overflow_wrap: OverflowWrap::Normal,
+            text_overflow: TextOverflow::Visible,
+            max_lines: 0,
+            line_height: LineHeight::Auto,
+            letter_spacing: 0.,
+            line_breaking: LineBreaking::Greedy,
+            vertical_alignment: VerticalAlignment::Top,
+            selection: None,
+            caret: None,
+            margin: MarginConfig::no_margin(),
+            self_alignment: None,
+        }
+    }
+}
+
 impl Default for AlignmentConfig {
     fn default() -> Self {
         AlignmentConfig {
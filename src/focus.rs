@@ -0,0 +1,167 @@
+use crate::data_type::{BoundingBox, ElementId, Key};
+
+/*
+    Focus navigation.
+
+    Tab walks the focusable elements in tab order, wrapping around at both ends. Arrow keys
+    go to the closest element whose center lies in their direction, elements off to the side
+    counting as further away than the ones straight ahead.
+*/
+
+/// A focusable element of the current layout.
+pub(crate) struct Focusable {
+    pub id: ElementId,
+    pub tab_index: Option<u32>,
+    pub bounds: BoundingBox,
+}
+
+/**
+ * The element after `current` in tab order, or before it when going `backward`.
+ * Without a focused element, the first or last one. The focusables are in the order
+ * they were declared.
+ */
+pub(crate) fn tab_target(
+    focusables: &[Focusable],
+    current: Option<ElementId>,
+    backward: bool,
+) -> Option<ElementId> {
+    let mut order: Vec<&Focusable> = focusables.iter().collect();
+
+    // the sort is stable, so elements without a tab index stay in declaration order.
+    order.sort_by_key(|focusable| match focusable.tab_index {
+        Some(tab_index) => (0, tab_index),
+        None => (1, 0),
+    });
+
+    let count = order.len();
+    if count == 0 {
+        return None;
+    }
+
+    let position = current.and_then(|id| order.iter().position(|focusable| focusable.id == id));
+    let index = match (position, backward) {
+        (Some(position), false) => (position + 1) % count,
+        (Some(position), true) => (position + count - 1) % count,
+        (None, false) => 0,
+        (None, true) => count - 1,
+    };
+
+    Some(order[index].id)
+}
+
+/// The element the arrow `key` leads to from `current`, if any lies in that direction.
+pub(crate) fn spatial_target(
+    focusables: &[Focusable],
+    current: ElementId,
+    key: Key,
+) -> Option<ElementId> {
+    let from = center(
+        &focusables
+            .iter()
+            .find(|focusable| focusable.id == current)?
+            .bounds,
+    );
+
+    focusables
+        .iter()
+        .filter(|focusable| focusable.id != current)
+        .filter_map(|focusable| {
+            let to = center(&focusable.bounds);
+
+            let (along, across) = match key {
+                Key::Left => (from.0 - to.0, to.1 - from.1),
+                Key::Right => (to.0 - from.0, to.1 - from.1),
+                Key::Up => (from.1 - to.1, to.0 - from.0),
+                Key::Down => (to.1 - from.1, to.0 - from.0),
+                _ => return None,
+            };
+
+            (along > 0.).then_some((along + across.abs() * 2., focusable.id))
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, id)| id)
+}
+
+fn center(bounds: &BoundingBox) -> (f32, f32) {
+    (bounds.x + bounds.width / 2., bounds.y + bounds.height / 2.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 20x20 focusable element with its top left corner at `x`, `y`.
+    fn focusable(id: u64, tab_index: Option<u32>, x: f32, y: f32) -> Focusable {
+        Focusable {
+            id: ElementId(id),
+            tab_index,
+            bounds: BoundingBox {
+                x,
+                y,
+                width: 20.,
+                height: 20.,
+            },
+        }
+    }
+
+    #[test]
+    fn tab_order_puts_tab_indices_first_and_wraps() {
+        let focusables = [
+            focusable(1, None, 0., 0.),
+            focusable(2, Some(2), 0., 0.),
+            focusable(3, None, 0., 0.),
+            focusable(4, Some(1), 0., 0.),
+        ];
+
+        let mut order = vec![];
+        let mut current = None;
+        for _ in 0..5 {
+            current = tab_target(&focusables, current, false);
+            order.push(current.unwrap().0);
+        }
+
+        assert_eq!(order, [4, 2, 1, 3, 4]);
+    }
+
+    #[test]
+    fn shift_tab_walks_backward_from_the_last_element() {
+        let focusables = [
+            focusable(1, None, 0., 0.),
+            focusable(2, None, 0., 0.),
+            focusable(3, None, 0., 0.),
+        ];
+
+        assert_eq!(tab_target(&focusables, None, true), Some(ElementId(3)));
+        assert_eq!(
+            tab_target(&focusables, Some(ElementId(1)), true),
+            Some(ElementId(3))
+        );
+        assert_eq!(
+            tab_target(&focusables, Some(ElementId(3)), true),
+            Some(ElementId(2))
+        );
+        assert_eq!(tab_target(&[], None, false), None);
+    }
+
+    #[test]
+    fn arrows_prefer_elements_straight_ahead() {
+        let focusables = [
+            focusable(1, None, 0., 0.),
+            focusable(2, None, 100., 0.),
+            focusable(3, None, 60., 50.),
+            focusable(4, None, 0., 100.),
+        ];
+
+        let from = ElementId(1);
+        assert_eq!(
+            spatial_target(&focusables, from, Key::Right),
+            Some(ElementId(2))
+        );
+        assert_eq!(
+            spatial_target(&focusables, from, Key::Down),
+            Some(ElementId(4))
+        );
+        assert_eq!(spatial_target(&focusables, from, Key::Left), None);
+        assert_eq!(spatial_target(&focusables, from, Key::Up), None);
+    }
+}
@@ -0,0 +1,139 @@
+use std::ops::Range;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::data_type::TextInputState;
+
+/*
+    Text input editing.
+
+    The caret and the selection anchor are byte indices into the text, always on grapheme
+    boundaries. Moving without extending the selection brings the anchor along with the
+    caret, and typing replaces whatever is selected.
+*/
+
+impl TextInputState {
+    pub fn new(multiline: bool) -> TextInputState {
+        TextInputState {
+            text: String::new(),
+            cursor: 0,
+            anchor: 0,
+            scroll: 0.,
+            multiline,
+        }
+    }
+
+    pub fn selection(&self) -> Range<usize> {
+        self.cursor.min(self.anchor)..self.cursor.max(self.anchor)
+    }
+
+    /// Replace the whole text, with the caret at its end.
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.text.len();
+        self.anchor = self.cursor;
+    }
+
+    /// Replace the selection with typed text. Returns whether the text changed.
+    pub fn insert(&mut self, text: &str) -> bool {
+        let text = self.accepted_text(text);
+        let selection = self.selection();
+
+        if text.is_empty() && selection.is_empty() {
+            return false;
+        }
+
+        self.text.replace_range(selection.clone(), &text);
+        self.cursor = selection.start + text.len();
+        self.anchor = self.cursor;
+        true
+    }
+
+    /// Delete the selection, or the grapheme before the caret. Returns whether the text changed.
+    pub fn delete_backward(&mut self) -> bool {
+        if self.anchor == self.cursor {
+            self.anchor = previous_boundary(&self.text, self.cursor);
+        }
+
+        self.insert("")
+    }
+
+    /// Delete the selection, or the grapheme after the caret. Returns whether the text changed.
+    pub fn delete_forward(&mut self) -> bool {
+        if self.anchor == self.cursor {
+            self.anchor = next_boundary(&self.text, self.cursor);
+        }
+
+        self.insert("")
+    }
+
+    /// Move the caret one grapheme, or to the edge of the selection when collapsing it.
+    pub fn move_horizontally(&mut self, forward: bool, extend: bool) {
+        let selection = self.selection();
+
+        let index = match (extend || selection.is_empty(), forward) {
+            (true, true) => next_boundary(&self.text, self.cursor),
+            (true, false) => previous_boundary(&self.text, self.cursor),
+            (false, true) => selection.end,
+            (false, false) => selection.start,
+        };
+
+        self.move_to(index, extend);
+    }
+
+    /// Move the caret to the start or the end of its paragraph.
+    pub fn move_to_paragraph_edge(&mut self, end: bool, extend: bool) {
+        let index = if end {
+            self.text[self.cursor..]
+                .find('\n')
+                .map_or(self.text.len(), |offset| self.cursor + offset)
+        } else {
+            self.text[..self.cursor]
+                .rfind('\n')
+                .map_or(0, |offset| offset + 1)
+        };
+
+        self.move_to(index, extend);
+    }
+
+    /// Move the caret to the grapheme boundary at or before `index`.
+    pub fn move_to(&mut self, index: usize, extend: bool) {
+        self.cursor = boundary_at(&self.text, index);
+
+        if !extend {
+            self.anchor = self.cursor;
+        }
+    }
+
+    /// Control characters are dropped, line breaks and tabs only make it into multi-line text.
+    fn accepted_text(&self, text: &str) -> String {
+        text.chars()
+            .filter(|character| {
+                !character.is_control() || (self.multiline && matches!(character, '\n' | '\t'))
+            })
+            .collect()
+    }
+}
+
+fn boundary_at(text: &str, index: usize) -> usize {
+    text.grapheme_indices(true)
+        .map(|(boundary, _)| boundary)
+        .chain([text.len()])
+        .take_while(|boundary| *boundary <= index)
+        .last()
+        .unwrap_or(0)
+}
+
+fn previous_boundary(text: &str, index: usize) -> usize {
+    text[..index]
+        .grapheme_indices(true)
+        .next_back()
+        .map_or(0, |(boundary, _)| boundary)
+}
+
+fn next_boundary(text: &str, index: usize) -> usize {
+    text[index..]
+        .graphemes(true)
+        .next()
+        .map_or(text.len(), |grapheme| index + grapheme.len())
+}
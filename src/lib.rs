@@ -1,4 +1,8 @@
 pub mod core;
 pub mod data_type;
+mod focus;
+mod input;
+mod text;
+pub mod widgets;
 
-pub use crate::data_type::*;
\ No newline at end of file
+pub use crate::data_type::*;
@@ -1,8 +1,5 @@
 use macroquad::prelude::*;
-use ui_library::{
-    ElementConfig, FixedDimensionConfig, GrowDimensionConfig, LayoutContext, TextConfig,
-    TextMeasurement,
-};
+use ui_library::{ElementConfig, LayoutContext, TextConfig, TextMeasurement};
 
 fn window_config() -> Conf {
     Conf {
@@ -20,9 +17,9 @@ fn measurement_fn(text: &str, font_id: u32, font_size: u16) -> TextMeasurement {
     let measure_res = measure_text(text, None, font_size, 1.);
     TextMeasurement {
         width: measure_res.width,
-        height: measure_res.height,
+        ascent: measure_res.offset_y,
+        descent: measure_res.height - measure_res.offset_y,
         x_offset: 0.,
-        y_offset: measure_res.offset_y,
     }
 }
 
@@ -54,14 +51,8 @@ Duis vel vehicula ante, vitae scelerisque nunc. In semper, sem vel dignissim ali
         layout_context.add_text(
             text,
             TextConfig {
-                width: ui_library::DimensionConfig::Grow(GrowDimensionConfig {
-                    min_size: 0.,
-                    max_size: 0.,
-                }),
-                height: ui_library::DimensionConfig::Grow(GrowDimensionConfig {
-                    min_size: 0.,
-                    max_size: 0.,
-                }),
+                width: ui_library::DimensionConfig::grow(),
+                height: ui_library::DimensionConfig::grow(),
                 font_id: 0,
                 font_size: 16,
                 font_color: ui_library::Color {
@@ -71,15 +62,13 @@ Duis vel vehicula ante, vitae scelerisque nunc. In semper, sem vel dignissim ali
                     a: 255,
                 },
                 break_word: true,
+                ..Default::default()
             },
         );
         layout_context.add_element(
             ElementConfig::new(ElementConfig {
-                width: ui_library::DimensionConfig::Fixed(FixedDimensionConfig { size: width }),
-                height: ui_library::DimensionConfig::Grow(GrowDimensionConfig {
-                    min_size: 0.,
-                    max_size: 0.,
-                }),
+                width: ui_library::DimensionConfig::fixed(width),
+                height: ui_library::DimensionConfig::grow(),
                 color: ui_library::Color {
                     r: 254,
                     g: 0,
@@ -88,7 +77,7 @@ Duis vel vehicula ante, vitae scelerisque nunc. In semper, sem vel dignissim ali
                 },
                 ..Default::default()
             }),
-            |_| {}
+            |_| {},
         );
 
         let render_commands = layout_context.end_layout();
@@ -100,7 +89,7 @@ Duis vel vehicula ante, vitae scelerisque nunc. In semper, sem vel dignissim ali
                     draw_text(
                         &text_render_data.text,
                         render_command.position.x,
-                        render_command.position.y,
+                        text_render_data.baseline,
                         text_render_data.font_size as f32,
                         Color::from_rgba(
                             text_render_data.font_color.r,
@@ -131,5 +120,3 @@ Duis vel vehicula ante, vitae scelerisque nunc. In semper, sem vel dignissim ali
         next_frame().await
     }
 }
-
-// multiline printing: The base position is y pos - offset, then add font-size for each line
@@ -0,0 +1,1334 @@
+use std::{ops::Range, rc::Rc};
+
+use unicode_bidi::{BidiInfo, Direction, Level, ParagraphBidiInfo, get_base_direction};
+use unicode_linebreak::linebreaks;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::data_type::*;
+
+/*
+    Text layout helpers.
+
+    The text of an element is first split into paragraphs at hard line breaks, each
+    paragraph is then cut into tokens (words and white space), measured, and broken
+    into lines. Lines are finally cut into pieces that can be handed to the renderer
+    as-is: tabs never reach the renderer, they only move the next piece forward.
+
+    Every character belongs to a span, which holds its font. Tokens never cross span
+    boundaries, so a word made of several spans is several tokens joined together,
+    which lines cannot break between.
+
+    A "word" is whatever lies between two line break opportunities, as found by the
+    Unicode line breaking algorithm (UAX #14). Most often it is an actual word, but in
+    Chinese or Japanese it is usually a single character, and "well-known" is two words.
+
+    Paragraphs are laid out in logical order, and each line is only reordered visually
+    when it is cut into pieces, following the Unicode bidirectional algorithm (UAX #9).
+    Tokens never cross a change of embedding level either. The text of a piece stays in
+    logical order, right-to-left pieces are flagged so the renderer shapes them that way.
+*/
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum TokenKind {
+    Word,
+    Space,
+    Tab,
+}
+
+/// A hard line break free part of the text, with white space already applied.
+pub(crate) struct Paragraph {
+    pub text: String,
+    /// Which span each part of the text comes from, in order. Never empty.
+    pub runs: Vec<TextRun>,
+    /// Embedding level of each byte of the text.
+    pub levels: Vec<Level>,
+    pub level: Level,
+    /// Index in the element's text of each byte of the text, and of its end.
+    pub source: Vec<usize>,
+}
+
+pub(crate) struct TextRun {
+    pub range: Range<usize>,
+    pub span: usize,
+}
+
+pub(crate) struct TextToken {
+    pub kind: TokenKind,
+    pub range: Range<usize>,
+    pub span: usize,
+    pub level: Level,
+    pub measurement: TextMeasurement,
+    /// The word was broken after this token and a hyphen is drawn after it (and measured with it).
+    pub hyphen: bool,
+    /// Lines cannot break before this word, it continues the previous one (from another
+    /// span, or past white space that offers no break opportunity).
+    pub joined: bool,
+}
+
+pub(crate) struct TextLine {
+    /// Index range into the paragraph's tokens, trailing white space excluded.
+    pub tokens: Range<usize>,
+    pub width: f32,
+    pub ascent: f32,
+    pub descent: f32,
+}
+
+/// A part of a line that is drawn in one go, positioned relative to the line's start.
+pub(crate) struct LinePiece {
+    pub range: Range<usize>,
+    pub span: usize,
+    pub rtl: bool,
+    /// Text drawn right after the range, which is not part of the source text (e.g. a hyphen).
+    pub suffix: &'static str,
+    pub x: f32,
+    pub width: f32,
+}
+
+pub(crate) const HYPHEN: &str = "-";
+
+/**
+ * Split the text at hard line breaks, applying the white space mode to each paragraph.
+ *
+ * When collapsing, every run of spaces and tabs turns into a single space (belonging to
+ * the span the run starts in), and leading and trailing ones are dropped.
+ *
+ * Paragraphs take the direction of their first strong character. Without one, they are
+ * right-to-left in a mirrored layout and left-to-right otherwise.
+ */
+pub(crate) fn paragraphs(
+    text: &str,
+    spans: &[InternalTextSpan],
+    white_space: WhiteSpace,
+    mirrored: bool,
+) -> Vec<Paragraph> {
+    let mut paragraphs: Vec<Paragraph> = Vec::new();
+    let mut start = 0;
+
+    for line in text.split('\n') {
+        let source = line.strip_suffix('\r').unwrap_or(line);
+        let mut paragraph = Paragraph {
+            text: String::with_capacity(source.len()),
+            runs: Vec::new(),
+            levels: Vec::new(),
+            level: Level::ltr(),
+            source: Vec::with_capacity(source.len() + 1),
+        };
+        let mut pending_space: Option<(usize, usize)> = None;
+
+        for (index, character) in source.char_indices() {
+            let span = span_at(spans, start + index);
+
+            if matches!(white_space, WhiteSpace::Collapse)
+                && (character == ' ' || character == '\t')
+            {
+                if !paragraph.text.is_empty() && pending_space.is_none() {
+                    pending_space = Some((span, start + index));
+                }
+                continue;
+            }
+
+            if let Some((space_span, space_source)) = pending_space.take() {
+                paragraph.push(' ', space_span, space_source);
+            }
+
+            paragraph.push(character, span, start + index);
+        }
+
+        paragraph.source.push(start + source.len());
+
+        // an empty paragraph still needs a span to be measured with.
+        if paragraph.runs.is_empty() {
+            paragraph.runs.push(TextRun {
+                range: 0..0,
+                span: span_at(spans, start),
+            });
+        }
+
+        let default_level = match get_base_direction(paragraph.text.as_str()) {
+            Direction::Ltr => Level::ltr(),
+            Direction::Rtl => Level::rtl(),
+            Direction::Mixed if mirrored => Level::rtl(),
+            Direction::Mixed => Level::ltr(),
+        };
+
+        let bidi_info = ParagraphBidiInfo::new(&paragraph.text, Some(default_level));
+        paragraph.level = bidi_info.paragraph_level;
+        paragraph.levels = bidi_info.levels;
+
+        paragraphs.push(paragraph);
+        start += line.len() + 1;
+    }
+
+    paragraphs
+}
+
+impl Paragraph {
+    fn push(&mut self, character: char, span: usize, source: usize) {
+        let start = self.text.len();
+        self.text.push(character);
+        self.source.extend(source..source + character.len_utf8());
+
+        match self.runs.last_mut() {
+            Some(run) if run.span == span => run.range.end = self.text.len(),
+            _ => self.runs.push(TextRun {
+                range: start..self.text.len(),
+                span,
+            }),
+        }
+    }
+}
+
+/// Index of the span holding the byte at `index`, or the last span past the end of the text.
+fn span_at(spans: &[InternalTextSpan], index: usize) -> usize {
+    spans
+        .iter()
+        .position(|span| index < span.range.end)
+        .unwrap_or(spans.len().saturating_sub(1))
+}
+
+/**
+ * Cut a paragraph into words, runs of spaces, and tabs (one token per tab).
+ *
+ * Only regular spaces are white space: a non-breaking space is part of its word, and the
+ * mandatory breaks UAX #14 knows besides hard line breaks are treated as allowed ones.
+ */
+pub(crate) fn tokenize(
+    paragraph: &Paragraph,
+    mut measure: impl FnMut(&str, usize) -> TextMeasurement,
+) -> Vec<TextToken> {
+    let mut tokens: Vec<TextToken> = Vec::new();
+    let mut break_opportunities = linebreaks(&paragraph.text)
+        .map(|(index, _)| index)
+        .peekable();
+
+    for run in &paragraph.runs {
+        for (index, character) in paragraph.text[run.range.clone()].char_indices() {
+            let kind = match character {
+                ' ' => TokenKind::Space,
+                '\t' => TokenKind::Tab,
+                _ => TokenKind::Word,
+            };
+            let start = run.range.start + index;
+            let end = start + character.len_utf8();
+            let level = paragraph.levels[start];
+
+            while break_opportunities
+                .next_if(|&index| index < start)
+                .is_some()
+            {}
+            let can_break = break_opportunities.peek() == Some(&start);
+
+            match tokens.last_mut() {
+                Some(token)
+                    if token.kind == kind
+                        && token.span == run.span
+                        && token.level == level
+                        && kind != TokenKind::Tab
+                        && !(kind == TokenKind::Word && can_break) =>
+                {
+                    token.range.end = end
+                }
+                last_token => {
+                    // e.g. a closing bracket after a space still cannot start a line.
+                    let joined = kind == TokenKind::Word && !can_break && last_token.is_some();
+
+                    tokens.push(TextToken {
+                        kind,
+                        range: start..end,
+                        span: run.span,
+                        level,
+                        measurement: TextMeasurement::default(),
+                        hyphen: false,
+                        joined,
+                    })
+                }
+            }
+        }
+    }
+
+    for token in &mut tokens {
+        token.measurement = match token.kind {
+            TokenKind::Word => measure(&paragraph.text[token.range.clone()], token.span),
+            TokenKind::Space => TextMeasurement {
+                width: measure(" ", token.span).width * token.range.len() as f32,
+                ..Default::default()
+            },
+            // the advance of a tab depends on where it lands, see `tab_advance`.
+            TokenKind::Tab => TextMeasurement::default(),
+        };
+    }
+
+    tokens
+}
+
+/**
+ * Break every word wider than `max_width` into parts that fit, at grapheme boundaries.
+ *
+ * Each part is as long as possible, found by measuring prefixes of the word (through the
+ * measurement cache, like any other word). Parts always hold at least one grapheme, so
+ * a single grapheme wider than `max_width` still overflows. In a word made of several
+ * spans, the first part of a span also has to leave room for the spans before it.
+ */
+pub(crate) fn break_long_words(
+    paragraph: &str,
+    tokens: Vec<TextToken>,
+    max_width: f32,
+    overflow_wrap: OverflowWrap,
+    mut measure: impl FnMut(&str, usize) -> TextMeasurement,
+) -> Vec<TextToken> {
+    let hyphen = match overflow_wrap {
+        OverflowWrap::Normal => return tokens,
+        OverflowWrap::BreakWord => false,
+        OverflowWrap::Hyphenate => true,
+    };
+
+    let mut broken_tokens: Vec<TextToken> = Vec::with_capacity(tokens.len());
+
+    // width of the word the current token belongs to, up to the token.
+    let mut word_width = 0.;
+
+    for token in tokens {
+        if !token.joined {
+            word_width = 0.;
+        }
+
+        let available_width = (max_width - word_width).max(0.);
+
+        if token.kind != TokenKind::Word || token.measurement.width <= available_width {
+            word_width += token.measurement.width;
+            broken_tokens.push(token);
+            continue;
+        }
+
+        let span = token.span;
+        let hyphen_width = if hyphen {
+            measure(HYPHEN, span).width
+        } else {
+            0.
+        };
+        let mut joined = token.joined;
+        let mut rest = token.range.clone();
+        let mut rest_measurement = token.measurement;
+        let mut available_width = available_width;
+
+        while rest_measurement.width > available_width {
+            let word = &paragraph[rest.clone()];
+            let boundaries: Vec<usize> = word
+                .grapheme_indices(true)
+                .skip(1)
+                .map(|(index, _)| index)
+                .collect();
+
+            if boundaries.is_empty() {
+                break;
+            }
+
+            // find the longest prefix that still fits, keeping at least one grapheme.
+            let low = last_fitting(&boundaries, |end| {
+                measure(&word[..end], span).width + hyphen_width <= available_width
+            })
+            .unwrap_or(0);
+            let mut part_measurement = measure(&word[..boundaries[low]], span);
+
+            let part_end = rest.start + boundaries[low];
+            part_measurement.width += hyphen_width;
+
+            broken_tokens.push(TextToken {
+                kind: TokenKind::Word,
+                range: rest.start..part_end,
+                span,
+                level: token.level,
+                measurement: part_measurement,
+                hyphen,
+                joined,
+            });
+
+            // the line always breaks after a part, which ends the joined word.
+            joined = false;
+            available_width = max_width;
+            rest.start = part_end;
+            rest_measurement = measure(&paragraph[rest.clone()], span);
+        }
+
+        // the last part starts a new line, unless nothing was broken off.
+        word_width = if joined { word_width } else { 0. } + rest_measurement.width;
+
+        broken_tokens.push(TextToken {
+            kind: TokenKind::Word,
+            range: rest,
+            span,
+            level: token.level,
+            measurement: rest_measurement,
+            hyphen: false,
+            joined,
+        });
+    }
+
+    broken_tokens
+}
+
+/**
+ * Binary search the index of the last boundary for which `fits` holds, assuming it holds
+ * for all boundaries up to some point and for none after (text only gets wider).
+ */
+fn last_fitting(boundaries: &[usize], mut fits: impl FnMut(usize) -> bool) -> Option<usize> {
+    if boundaries.is_empty() || !fits(boundaries[0]) {
+        return None;
+    }
+
+    let mut low = 0;
+    let mut high = boundaries.len();
+
+    while high - low > 1 {
+        let middle = (low + high) / 2;
+
+        if fits(boundaries[middle]) {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+
+    Some(low)
+}
+
+/// Distance from `x` to the next tab stop.
+pub(crate) fn tab_advance(x: f32, tab_stop: f32) -> f32 {
+    if tab_stop <= 0. {
+        return 0.;
+    }
+
+    tab_stop - x.rem_euclid(tab_stop)
+}
+
+fn token_advance(token: &TextToken, x: f32, tab_stop: f32) -> f32 {
+    match token.kind {
+        TokenKind::Tab => tab_advance(x, tab_stop),
+        _ => token.measurement.width,
+    }
+}
+
+/**
+ * Greedily break a paragraph's tokens into lines no wider than `max_width`.
+ *
+ * Lines only break before a word, and the white space in front of that word hangs at the
+ * end of the previous line without counting toward its width. A word wider than
+ * `max_width` overflows on its own line. A paragraph without any word still makes one
+ * line, as tall as a space.
+ *
+ * Words of a line share their baseline: the line is tall enough for the highest ascent
+ * and the deepest descent among them.
+ */
+pub(crate) fn break_lines(
+    tokens: &[TextToken],
+    max_width: f32,
+    tab_stop: f32,
+    space_measurement: TextMeasurement,
+) -> Vec<TextLine> {
+    let mut lines: Vec<TextLine> = Vec::new();
+    let mut line = empty_line(0, space_measurement);
+    let mut line_has_word = false;
+    let mut x = 0.;
+
+    for (index, token) in tokens.iter().enumerate() {
+        if token.kind == TokenKind::Word {
+            // the parts of a broken word always end their line.
+            let after_break = index > 0 && tokens[index - 1].hyphen;
+
+            // the rest of a word spanning several spans has to fit along with its start.
+            let word_width = token.measurement.width + joined_width(&tokens[index + 1..]);
+
+            if line_has_word && !token.joined && (after_break || x + word_width > max_width) {
+                lines.push(line);
+                line = empty_line(index, space_measurement);
+                x = 0.;
+            }
+
+            // the first word replaces the placeholder height of an empty line.
+            if line_has_word {
+                line.ascent = line.ascent.max(token.measurement.ascent);
+                line.descent = line.descent.max(token.measurement.descent);
+            } else {
+                line.ascent = token.measurement.ascent;
+                line.descent = token.measurement.descent;
+            }
+
+            line_has_word = true;
+            x += token.measurement.width;
+            line.width = x;
+            line.tokens.end = index + 1;
+            continue;
+        }
+
+        x += token_advance(token, x, tab_stop);
+
+        // leading white space is kept (it only exists in preserve mode).
+        if !line_has_word {
+            line.width = x;
+            line.tokens.end = index + 1;
+        }
+    }
+
+    lines.push(line);
+    lines
+}
+
+/// Width of the words joined to the one before `tokens`, and of the white space between them.
+fn joined_width(tokens: &[TextToken]) -> f32 {
+    let mut width = 0.;
+    let mut white_space_width = 0.;
+
+    for token in tokens {
+        match token.kind {
+            TokenKind::Word if token.joined => {
+                width += white_space_width + token.measurement.width;
+                white_space_width = 0.;
+            }
+            TokenKind::Word => break,
+            _ => white_space_width += token.measurement.width,
+        }
+    }
+
+    width
+}
+
+fn empty_line(start: usize, space_measurement: TextMeasurement) -> TextLine {
+    TextLine {
+        tokens: start..start,
+        width: 0.,
+        ascent: space_measurement.ascent,
+        descent: space_measurement.descent,
+    }
+}
+
+/**
+ * Break a paragraph's tokens into lines no wider than `max_width`, choosing all the breaks
+ * together so the lines are as even as possible (in the manner of Knuth and Plass).
+ *
+ * Lines break at the same places as with `break_lines`, and the breaks minimize the sum of
+ * the squared space left at the end of every line but the last. A word wider than
+ * `max_width` still overflows on its own line, at a cost higher than any other choice.
+ *
+ * Returns the index of the first token of each line, to be turned into lines by
+ * `lines_from_breaks`.
+ */
+pub(crate) fn optimal_breaks(tokens: &[TextToken], max_width: f32, tab_stop: f32) -> Vec<usize> {
+    const OVERFLOW_COST: f64 = 1e12;
+
+    // a line can start at the beginning, or at any word that is not joined to the previous one.
+    let mut starts: Vec<usize> = vec![0];
+    let mut seen_word = false;
+    for (index, token) in tokens.iter().enumerate() {
+        if token.kind == TokenKind::Word {
+            if seen_word && !token.joined {
+                starts.push(index);
+            }
+            seen_word = true;
+        }
+    }
+
+    let slack_cost = |width: f32| (max_width - width) as f64 * (max_width - width) as f64;
+
+    // best[i] is the cost of the lines before starts[i], and the start of the last of them.
+    let mut best: Vec<(f64, usize)> = vec![(f64::INFINITY, 0); starts.len() + 1];
+    best[0] = (0., 0);
+
+    for (start_index, &start) in starts.iter().enumerate() {
+        let (cost_before, _) = best[start_index];
+        if cost_before.is_infinite() {
+            continue;
+        }
+
+        let mut x = 0.;
+        let mut width = 0.;
+        let mut word_count = 0;
+        let mut end_index = start_index + 1;
+
+        for (index, token) in tokens.iter().enumerate().skip(start) {
+            // the line can end before this token.
+            if end_index < starts.len() && starts[end_index] == index {
+                let cost = match word_count {
+                    _ if width <= max_width => slack_cost(width),
+                    1 => OVERFLOW_COST + slack_cost(width),
+                    _ => break,
+                };
+
+                if cost_before + cost < best[end_index].0 {
+                    best[end_index] = (cost_before + cost, start_index);
+                }
+
+                // adding words to an overflowing line never helps, and the parts of a
+                // broken word always end their line.
+                if width > max_width || tokens[index - 1].hyphen {
+                    break;
+                }
+
+                word_count += 1;
+                end_index += 1;
+            } else if token.kind == TokenKind::Word && word_count == 0 {
+                word_count = 1;
+            }
+
+            x += token_advance(token, x, tab_stop);
+
+            if token.kind == TokenKind::Word {
+                width = x;
+            }
+        }
+
+        // the last line only costs something when it overflows.
+        if end_index == starts.len() {
+            let cost = match word_count {
+                _ if width <= max_width => 0.,
+                0 | 1 => OVERFLOW_COST + slack_cost(width),
+                _ => continue,
+            };
+
+            if cost_before + cost < best[starts.len()].0 {
+                best[starts.len()] = (cost_before + cost, start_index);
+            }
+        }
+    }
+
+    let mut breaks: Vec<usize> = Vec::new();
+    let mut index = starts.len();
+    while index > 0 {
+        index = best[index].1;
+        breaks.push(starts[index]);
+    }
+
+    breaks.reverse();
+    breaks
+}
+
+/**
+ * What the optimal lines of a paragraph depend on, kept whole so a cached paragraph is
+ * only reused for the very same text, fonts and width.
+ */
+#[derive(PartialEq, Eq, Hash)]
+pub(crate) struct LineBreakKey {
+    text: String,
+    /// Range, font and font size of each run of the paragraph.
+    runs: Vec<(Range<usize>, u32, u16)>,
+    level: u8,
+    letter_spacing: u32,
+    max_width: u32,
+    tab_stop: u32,
+    overflow_wrap: OverflowWrap,
+}
+
+/// A paragraph broken into lines, kept from one frame to the next.
+pub(crate) struct BrokenParagraph {
+    pub tokens: Vec<TextToken>,
+    pub lines: Vec<TextLine>,
+}
+
+pub(crate) struct LineBreakEntry {
+    pub paragraph: Rc<BrokenParagraph>,
+    /// The paragraph was laid out since the cache was last pruned.
+    pub used: bool,
+}
+
+pub(crate) fn line_break_key(
+    paragraph: &Paragraph,
+    spans: &[InternalTextSpan],
+    letter_spacing: f32,
+    max_width: f32,
+    tab_stop: f32,
+    overflow_wrap: OverflowWrap,
+) -> LineBreakKey {
+    LineBreakKey {
+        text: paragraph.text.clone(),
+        runs: paragraph
+            .runs
+            .iter()
+            .map(|run| {
+                let span = &spans[run.span];
+                (run.range.clone(), span.font_id, span.font_size)
+            })
+            .collect(),
+        level: paragraph.level.number(),
+        letter_spacing: letter_spacing.to_bits(),
+        max_width: max_width.to_bits(),
+        tab_stop: tab_stop.to_bits(),
+        overflow_wrap,
+    }
+}
+
+/// Turn the index of the first token of each line into lines, measured like `break_lines` does.
+pub(crate) fn lines_from_breaks(
+    tokens: &[TextToken],
+    breaks: &[usize],
+    tab_stop: f32,
+    space_measurement: TextMeasurement,
+) -> Vec<TextLine> {
+    let mut lines: Vec<TextLine> = Vec::with_capacity(breaks.len());
+
+    for (line_index, &start) in breaks.iter().enumerate() {
+        let end = breaks.get(line_index + 1).copied().unwrap_or(tokens.len());
+        let mut line = empty_line(start, space_measurement);
+        let mut line_has_word = false;
+        let mut x = 0.;
+
+        for (index, token) in tokens.iter().enumerate().take(end).skip(start) {
+            x += token_advance(token, x, tab_stop);
+
+            if token.kind == TokenKind::Word {
+                if line_has_word {
+                    line.ascent = line.ascent.max(token.measurement.ascent);
+                    line.descent = line.descent.max(token.measurement.descent);
+                } else {
+                    line.ascent = token.measurement.ascent;
+                    line.descent = token.measurement.descent;
+                }
+
+                line_has_word = true;
+            }
+
+            if token.kind == TokenKind::Word || !line_has_word {
+                line.width = x;
+                line.tokens.end = index + 1;
+            }
+        }
+
+        lines.push(line);
+    }
+
+    lines
+}
+
+/**
+ * Indices of a line's tokens from left to right, along with their level on the line.
+ *
+ * Tabs, and the white space before them or at the end of the line, take the paragraph's
+ * level (rule L1), so they stay where the paragraph's direction puts them.
+ */
+fn visual_order(
+    tokens: &[TextToken],
+    line: &TextLine,
+    paragraph_level: Level,
+) -> Vec<(usize, Level)> {
+    let line_tokens = &tokens[line.tokens.clone()];
+    let mut levels: Vec<Level> = line_tokens.iter().map(|token| token.level).collect();
+
+    let mut trailing = true;
+    for (index, token) in line_tokens.iter().enumerate().rev() {
+        match token.kind {
+            TokenKind::Tab => {
+                levels[index] = paragraph_level;
+                trailing = true;
+            }
+            TokenKind::Space if trailing => levels[index] = paragraph_level,
+            _ => trailing = false,
+        }
+    }
+
+    BidiInfo::reorder_visual(&levels)
+        .into_iter()
+        .map(|index| (line.tokens.start + index, levels[index]))
+        .collect()
+}
+
+/**
+ * Cut a line into pieces at its tabs, from left to right.
+ *
+ * A piece only holds tokens of the same span and direction that follow each other in the
+ * text as well as on screen, so that it reads the same once shaped in its direction.
+ */
+pub(crate) fn line_pieces(
+    tokens: &[TextToken],
+    line: &TextLine,
+    paragraph_level: Level,
+    tab_stop: f32,
+) -> Vec<LinePiece> {
+    let mut pieces: Vec<LinePiece> = Vec::new();
+    let mut piece: Option<LinePiece> = None;
+    let mut previous_index = usize::MAX;
+    let mut x = 0.;
+
+    for (index, level) in visual_order(tokens, line, paragraph_level) {
+        let token = &tokens[index];
+        let rtl = level.is_rtl();
+        let advance = token_advance(token, x, tab_stop);
+
+        let continues = piece.as_ref().is_some_and(|piece| {
+            let next_index = if rtl {
+                previous_index.wrapping_sub(1)
+            } else {
+                previous_index.wrapping_add(1)
+            };
+
+            piece.span == token.span && piece.rtl == rtl && index == next_index
+        });
+
+        if !continues || token.kind == TokenKind::Tab {
+            pieces.extend(piece.take());
+        }
+
+        if token.kind != TokenKind::Tab {
+            let piece = piece.get_or_insert(LinePiece {
+                range: token.range.clone(),
+                span: token.span,
+                rtl,
+                suffix: "",
+                x,
+                width: 0.,
+            });
+            piece.range.start = piece.range.start.min(token.range.start);
+            piece.range.end = piece.range.end.max(token.range.end);
+            piece.width += advance;
+
+            if token.hyphen {
+                piece.suffix = HYPHEN;
+            }
+        }
+
+        previous_index = index;
+        x += advance;
+    }
+
+    pieces.extend(piece);
+    pieces
+}
+
+/**
+ * Cut a line into one piece per word (and span), from left to right, spreading the words
+ * so the line is `width` wide.
+ */
+pub(crate) fn justified_pieces(
+    tokens: &[TextToken],
+    line: &TextLine,
+    paragraph_level: Level,
+    tab_stop: f32,
+    width: f32,
+) -> Vec<LinePiece> {
+    let order = visual_order(tokens, line, paragraph_level);
+
+    // words joined to their neighbour on screen share its offset.
+    let mut previous_word: Option<usize> = None;
+    let starts_word: Vec<bool> = order
+        .iter()
+        .map(|&(index, _)| {
+            if tokens[index].kind != TokenKind::Word {
+                return false;
+            }
+
+            let joined = previous_word.is_some_and(|previous: usize| {
+                let later = previous.max(index);
+                previous.abs_diff(index) == 1 && tokens[later].joined
+            });
+            previous_word = Some(index);
+
+            !joined
+        })
+        .collect();
+
+    let word_count = starts_word.iter().filter(|starts| **starts).count();
+
+    let extra_spacing = if word_count > 1 {
+        (width - line.width) / (word_count - 1) as f32
+    } else {
+        0.
+    };
+
+    let mut pieces: Vec<LinePiece> = Vec::new();
+    let mut words_before = 0;
+    let mut x = 0.;
+
+    for (&(index, level), &starts_word) in order.iter().zip(&starts_word) {
+        let token = &tokens[index];
+
+        if token.kind == TokenKind::Word {
+            if starts_word && !pieces.is_empty() {
+                words_before += 1;
+            }
+
+            pieces.push(LinePiece {
+                range: token.range.clone(),
+                span: token.span,
+                rtl: level.is_rtl(),
+                suffix: if token.hyphen { HYPHEN } else { "" },
+                x: x + words_before as f32 * extra_spacing,
+                width: token.measurement.width,
+            });
+        }
+
+        x += token_advance(token, x, tab_stop);
+    }
+
+    pieces
+}
+
+/// The text of a piece as drawn, in logical order.
+pub(crate) fn piece_text(paragraph: &str, piece: &LinePiece) -> String {
+    paragraph[piece.range.clone()].to_string() + piece.suffix
+}
+
+/// Number of words in a line, used to decide whether it can be justified.
+pub(crate) fn word_count(tokens: &[TextToken], line: &TextLine) -> usize {
+    tokens[line.tokens.clone()]
+        .iter()
+        .filter(|token| token.kind == TokenKind::Word && !token.joined)
+        .count()
+}
+
+/**
+ * Keep only the first `line_count` lines.
+ *
+ * With an ellipsis, the last kept line gets it when lines were dropped, and every kept
+ * line wider than `max_width` is shortened to fit along with it.
+ */
+pub(crate) fn truncate_lines(
+    lines: &mut Vec<TextLayoutLine>,
+    line_count: usize,
+    max_width: f32,
+    text_overflow: &TextOverflow,
+    mut measure: impl FnMut(&str, usize) -> TextMeasurement,
+) {
+    const EPSILON: f32 = 0.01;
+
+    let cut = lines.len() > line_count;
+    lines.truncate(line_count);
+
+    let TextOverflow::Ellipsis(ellipsis) = text_overflow else {
+        return;
+    };
+
+    let last_line = lines.len().saturating_sub(1);
+
+    for (line_index, line) in lines.iter_mut().enumerate() {
+        if (cut && line_index == last_line) || line_width(line) > max_width + EPSILON {
+            ellipsize_line(line, max_width, ellipsis, &mut measure);
+        }
+    }
+}
+
+fn line_width(line: &TextLayoutLine) -> f32 {
+    match (line.fragments.first(), line.fragments.last()) {
+        (Some(first), Some(last)) => last.x + last.width - first.x,
+        _ => 0.,
+    }
+}
+
+/**
+ * End the line with the ellipsis, dropping as much of the end of the line as needed for
+ * it to fit in `max_width`. The ellipsis is measured together with the text it follows.
+ * The end of a right-to-left line is on its left.
+ */
+fn ellipsize_line(
+    line: &mut TextLayoutLine,
+    max_width: f32,
+    ellipsis: &str,
+    measure: &mut impl FnMut(&str, usize) -> TextMeasurement,
+) {
+    if line.rtl {
+        cut_line_start(line, max_width, ellipsis, measure);
+    } else {
+        cut_line_end(line, max_width, ellipsis, measure);
+    }
+
+    // alignment is applied again now that the line has its final width.
+    let width = line_width(line);
+    let line_x = match line.alignment {
+        TextAlignment::Left | TextAlignment::Justify => 0.,
+        TextAlignment::Center => (max_width - width) / 2.,
+        TextAlignment::Right => max_width - width,
+    };
+
+    let start_x = line.fragments.first().map_or(0., |fragment| fragment.x);
+    for fragment in &mut line.fragments {
+        fragment.x += line_x - start_x;
+    }
+}
+
+fn cut_line_end(
+    line: &mut TextLayoutLine,
+    max_width: f32,
+    ellipsis: &str,
+    measure: &mut impl FnMut(&str, usize) -> TextMeasurement,
+) {
+    // work from the left end of the line.
+    let start_x = line.fragments.first().map_or(0., |fragment| fragment.x);
+    for fragment in &mut line.fragments {
+        fragment.x -= start_x;
+    }
+
+    // the ellipsis takes the style of the text it replaces.
+    let mut span = line.fragments.last().map_or(0, |fragment| fragment.span);
+
+    while let Some(fragment) = line.fragments.last_mut() {
+        span = fragment.span;
+        let available_width = max_width - fragment.x;
+
+        // the right end of a right-to-left fragment is the start of its text.
+        if shorten_fragment(fragment, available_width, ellipsis, !fragment.rtl, measure) {
+            break;
+        }
+
+        line.fragments.pop();
+    }
+
+    // not even the ellipsis alone fits, show it anyway so the cut is visible.
+    if line.fragments.is_empty() {
+        let width = measure(ellipsis, span).width;
+        line.fragments
+            .push(ellipsis_fragment(line, ellipsis, span, false, 0., width));
+    }
+}
+
+/// Mirror image of `cut_line_end`, for right-to-left lines.
+fn cut_line_start(
+    line: &mut TextLayoutLine,
+    max_width: f32,
+    ellipsis: &str,
+    measure: &mut impl FnMut(&str, usize) -> TextMeasurement,
+) {
+    // work from the right end of the line, placed at `max_width`.
+    let end_x = line
+        .fragments
+        .last()
+        .map_or(0., |fragment| fragment.x + fragment.width);
+    for fragment in &mut line.fragments {
+        fragment.x += max_width - end_x;
+    }
+
+    let mut span = line.fragments.first().map_or(0, |fragment| fragment.span);
+
+    while let Some(fragment) = line.fragments.first_mut() {
+        span = fragment.span;
+        let available_width = fragment.x + fragment.width;
+
+        if shorten_fragment(fragment, available_width, ellipsis, fragment.rtl, measure) {
+            fragment.x = available_width - fragment.width;
+            break;
+        }
+
+        line.fragments.remove(0);
+    }
+
+    if line.fragments.is_empty() {
+        let width = measure(ellipsis, span).width;
+        line.fragments.push(ellipsis_fragment(
+            line,
+            ellipsis,
+            span,
+            true,
+            max_width - width,
+            width,
+        ));
+    }
+}
+
+/// A fragment showing only the ellipsis, at the start of the line's text.
+fn ellipsis_fragment(
+    line: &TextLayoutLine,
+    ellipsis: &str,
+    span: usize,
+    rtl: bool,
+    x: f32,
+    width: f32,
+) -> TextFragment {
+    TextFragment {
+        text: Rc::from(ellipsis),
+        span,
+        range: line.range.start..line.range.start,
+        paragraph: Rc::from(""),
+        source: Rc::from([line.range.start]),
+        paragraph_range: 0..0,
+        rtl,
+        x,
+        width,
+    }
+}
+
+/**
+ * Keep as much of the start (or the end) of the fragment's text as fits in `available_width`
+ * along with the ellipsis, which goes after it (or before it) in logical order.
+ * Returns false when nothing fits.
+ */
+fn shorten_fragment(
+    fragment: &mut TextFragment,
+    available_width: f32,
+    ellipsis: &str,
+    keep_start: bool,
+    measure: &mut impl FnMut(&str, usize) -> TextMeasurement,
+) -> bool {
+    let text = fragment.text.clone();
+    let span = fragment.span;
+
+    // the shortest kept part comes first, as `last_fitting` expects.
+    let mut boundaries: Vec<usize> = text
+        .grapheme_indices(true)
+        .map(|(index, _)| index)
+        .collect();
+    boundaries.push(text.len());
+    if !keep_start {
+        boundaries.reverse();
+    }
+
+    let with_ellipsis = |boundary: usize| {
+        if keep_start {
+            text[..boundary].trim_end().to_string() + ellipsis
+        } else {
+            ellipsis.to_string() + text[boundary..].trim_start()
+        }
+    };
+
+    let Some(index) = last_fitting(&boundaries, |boundary| {
+        measure(&with_ellipsis(boundary), span).width <= available_width
+    }) else {
+        return false;
+    };
+
+    let ellipsized = with_ellipsis(boundaries[index]);
+    fragment.width = measure(&ellipsized, span).width;
+    fragment.text = Rc::from(ellipsized);
+    true
+}
+
+/*
+    Caret geometry.
+
+    Fragments remember which part of the element's text they show, and where it is in their
+    paragraph as laid out. A position in the text is found on screen (and back) by measuring
+    the prefixes of that part of the paragraph with the fragment's font, so collapsed white
+    space takes no room. Characters hidden by an ellipsis all sit at the end of their fragment.
+*/
+
+/// The line the caret at `index` is on: the last one starting at or before it.
+pub(crate) fn caret_line(lines: &[TextLayoutLine], index: usize) -> usize {
+    lines
+        .iter()
+        .rposition(|line| line.range.start <= index)
+        .unwrap_or(0)
+}
+
+/// Horizontal position of the caret at `index` in a line `width` wide.
+pub(crate) fn caret_x(
+    line: &TextLayoutLine,
+    index: usize,
+    width: f32,
+    measure: &mut impl FnMut(&str, usize) -> TextMeasurement,
+) -> f32 {
+    // an empty line only has its alignment to go by.
+    if line.fragments.is_empty() {
+        return match line.alignment {
+            TextAlignment::Left | TextAlignment::Justify => 0.,
+            TextAlignment::Center => width / 2.,
+            TextAlignment::Right => width,
+        };
+    }
+
+    if let Some(fragment) = line
+        .fragments
+        .iter()
+        .find(|fragment| fragment.range.contains(&index) || fragment.range.end == index)
+    {
+        return fragment_caret_x(fragment, index, measure);
+    }
+
+    // an index between fragments (in collapsed white space) sticks to the one before.
+    let Some(fragment) = line
+        .fragments
+        .iter()
+        .filter(|fragment| fragment.range.end <= index)
+        .max_by_key(|fragment| fragment.range.end)
+    else {
+        let first = line
+            .fragments
+            .iter()
+            .min_by_key(|fragment| fragment.range.start)
+            .expect("The line has fragments.");
+
+        return fragment_caret_x(first, index, measure);
+    };
+
+    let x = fragment_caret_x(fragment, fragment.range.end, measure);
+
+    // white space hanging at the end of the line still moves the caret along.
+    if fragment.range.end != line.range.end {
+        return x;
+    }
+
+    let hanging_end = paragraph_offset(fragment, index);
+    let hanging_start = fragment.paragraph_range.end.min(hanging_end);
+    let hanging = measure(
+        &fragment.paragraph[hanging_start..hanging_end],
+        fragment.span,
+    )
+    .width;
+    if fragment.rtl {
+        x - hanging
+    } else {
+        x + hanging
+    }
+}
+
+/**
+ * Index in the fragment's paragraph of the character at `index` in the element's text, or
+ * of the character after it when it was collapsed away.
+ */
+fn paragraph_offset(fragment: &TextFragment, index: usize) -> usize {
+    let mut offset = fragment
+        .source
+        .partition_point(|&source| source < index)
+        .min(fragment.paragraph.len());
+
+    while !fragment.paragraph.is_char_boundary(offset) {
+        offset -= 1;
+    }
+
+    offset
+}
+
+fn fragment_caret_x(
+    fragment: &TextFragment,
+    index: usize,
+    measure: &mut impl FnMut(&str, usize) -> TextMeasurement,
+) -> f32 {
+    let range = &fragment.paragraph_range;
+    let offset = paragraph_offset(fragment, index).clamp(range.start, range.end);
+
+    let advance = measure(&fragment.paragraph[range.start..offset], fragment.span)
+        .width
+        .min(fragment.width);
+
+    if fragment.rtl {
+        fragment.x + fragment.width - advance
+    } else {
+        fragment.x + advance
+    }
+}
+
+/// Index of the grapheme boundary closest to the point, relative to the top left of the lines.
+pub(crate) fn index_at(
+    lines: &[TextLayoutLine],
+    x: f32,
+    y: f32,
+    measure: &mut impl FnMut(&str, usize) -> TextMeasurement,
+) -> usize {
+    // points above or below the text go to its first or last line.
+    let Some(line) = lines
+        .iter()
+        .find(|line| y < line.y + line.height)
+        .or(lines.last())
+    else {
+        return 0;
+    };
+
+    let distance = |fragment: &TextFragment| {
+        (fragment.x - x)
+            .max(x - fragment.x - fragment.width)
+            .max(0.)
+    };
+
+    let Some(fragment) = line
+        .fragments
+        .iter()
+        .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+    else {
+        return line.range.start;
+    };
+
+    let range = fragment.paragraph_range.clone();
+    let boundaries = fragment.paragraph[range.clone()]
+        .grapheme_indices(true)
+        .map(|(offset, _)| range.start + offset)
+        .chain([range.end]);
+
+    let mut closest = (f32::INFINITY, fragment.range.start);
+    for boundary in boundaries {
+        let index = fragment.source[boundary];
+        let distance = (fragment_caret_x(fragment, index, measure) - x).abs();
+
+        if distance < closest.0 {
+            closest = (distance, index);
+        }
+    }
+
+    closest.1
+}
+
+/**
+ * Left and right ends of the selected parts of a line. The white space between two
+ * fragments is part of the highlight when it is selected as well.
+ */
+pub(crate) fn selection_extents(
+    line: &TextLayoutLine,
+    selection: Range<usize>,
+    measure: &mut impl FnMut(&str, usize) -> TextMeasurement,
+) -> Vec<(f32, f32)> {
+    let mut extents: Vec<(f32, f32)> = Vec::new();
+    let mut previous: Option<&TextFragment> = None;
+
+    for fragment in &line.fragments {
+        let start = selection.start.max(fragment.range.start);
+        let end = selection.end.min(fragment.range.end);
+
+        if start >= end {
+            previous = None;
+            continue;
+        }
+
+        let start_x = fragment_caret_x(fragment, start, measure);
+        let end_x = fragment_caret_x(fragment, end, measure);
+        let (left, right) = (start_x.min(end_x), start_x.max(end_x));
+
+        let gap_selected = previous.is_some_and(|previous| {
+            let gap = if previous.range.end <= fragment.range.start {
+                previous.range.end..fragment.range.start
+            } else {
+                fragment.range.end..previous.range.start
+            };
+
+            selection.start <= gap.start && gap.end <= selection.end
+        });
+
+        match extents.last_mut() {
+            Some(extent) if gap_selected => extent.1 = right,
+            _ => extents.push((left, right)),
+        }
+
+        previous = Some(fragment);
+    }
+
+    extents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tokens of a single paragraph, where every character is 8 wide.
+    fn tokens(text: &str) -> Vec<TextToken> {
+        let spans = [InternalTextSpan {
+            range: 0..text.len(),
+            font_id: 0,
+            font_size: 16,
+            font_color: Color::default(),
+        }];
+        let paragraphs = paragraphs(text, &spans, WhiteSpace::Collapse, false);
+
+        tokenize(&paragraphs[0], |word, _| TextMeasurement {
+            width: word.chars().count() as f32 * 8.,
+            ascent: 12.,
+            descent: 4.,
+            x_offset: 0.,
+        })
+    }
+
+    #[test]
+    fn optimal_breaks_even_out_lines() {
+        // greedy breaking gives "aaa bb", "cc", "ddddd".
+        let tokens = tokens("aaa bb cc ddddd");
+
+        assert_eq!(optimal_breaks(&tokens, 48., 0.), [0, 2, 6]);
+    }
+
+    #[test]
+    fn optimal_breaks_keep_a_fitting_paragraph_on_one_line() {
+        let tokens = tokens("aaa bb cc");
+
+        assert_eq!(optimal_breaks(&tokens, 72., 0.), [0]);
+    }
+}
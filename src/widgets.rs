@@ -0,0 +1,1709 @@
+use std::ops::{Range, RangeInclusive};
+
+use crate::data_type::*;
+
+/*
+    Standard widgets, declared through the same calls as any other element.
+
+    Every widget is an element with an id that can take focus, unless disabled. It is
+    clicked by a press and a release both over it, or by Enter while it has focus. Values
+    belong to the caller: widgets edit them in place and say so in their response.
+
+    Hover and presses are found with the last layout, like every other pointer query.
+*/
+
+/// Label of the floating list of a dropdown or context menu.
+const MENU_LIST: &str = "menu";
+
+/// Label of the element holding the tabs of a tab container.
+const TAB_HEADER: &str = "header";
+/// Label of the element holding the active panel of a tab container.
+const TAB_PANEL: &str = "panel";
+
+/// Label of the floating element of a tooltip.
+const TOOLTIP: &str = "tooltip";
+
+const TRANSPARENT: Color = Color {
+    r: 0,
+    g: 0,
+    b: 0,
+    a: 0,
+};
+
+/// Color of a widget's body in each interaction state.
+#[derive(Clone, Copy)]
+pub struct WidgetColors {
+    pub idle: Color,
+    pub hovered: Color,
+    pub pressed: Color,
+    pub focused: Color,
+    pub disabled: Color,
+}
+
+#[derive(Clone, Copy)]
+pub struct WidgetConfig {
+    pub width: DimensionConfig,
+    pub height: DimensionConfig,
+    pub padding: PaddingConfig,
+    pub font_id: u32,
+    pub font_size: u16,
+    pub font_color: Color,
+    /// The button, the box of a checkbox or radio button, the track of a toggle or slider.
+    pub colors: WidgetColors,
+    /// What shows a value: the check, the radio dot, the knob of an enabled toggle, the slider.
+    pub accent_color: Color,
+    pub disabled: bool,
+}
+
+/// What happened to a widget while it was declared.
+#[derive(Clone, Copy, Default)]
+pub struct WidgetResponse {
+    pub hovered: bool,
+    /// The pointer was pressed on the widget and is still held down.
+    pub pressed: bool,
+    pub focused: bool,
+    pub clicked: bool,
+    /// The widget edited its value.
+    pub changed: bool,
+}
+
+/// How the tabs of a tab container share its header.
+#[derive(Clone, Copy)]
+pub enum TabWidth {
+    /// Every tab is as wide as the widest one, and they all grow to fill the header.
+    Equal,
+    /// Every tab fits its label.
+    Fit,
+}
+
+/// Where and how a tooltip shows, attached to its target like a floating element.
+#[derive(Clone, Copy)]
+pub struct TooltipConfig {
+    /// Seconds the pointer has to stay over the target before the tooltip shows.
+    pub delay: f32,
+    /// The point of the tooltip that is placed on `target_point` of the target.
+    pub element_point: AttachPoint,
+    pub target_point: AttachPoint,
+    pub offset: Position,
+    pub padding: PaddingConfig,
+    pub color: Color,
+    pub font_id: u32,
+    pub font_size: u16,
+    pub font_color: Color,
+}
+
+/// How tall the rows of a virtual list are.
+#[derive(Clone, Copy)]
+pub enum RowHeight {
+    /// Every row has this height, which they are given.
+    Fixed(f32),
+    /// Rows fit their content and are assumed this tall until they were first declared.
+    Estimated(f32),
+}
+
+/// The size of a virtual list, which should not fit its rows, and how they are laid out.
+#[derive(Clone, Copy)]
+pub struct VirtualListConfig {
+    pub width: DimensionConfig,
+    pub height: DimensionConfig,
+    pub row_height: RowHeight,
+    pub color: Color,
+}
+
+/// Where a virtual list is scrolled, with what a scrollbar needs to show it.
+#[derive(Clone)]
+pub struct VirtualListResponse {
+    /// Height of all the rows together.
+    pub content_height: f32,
+    /// Height of the list, through which the rows are seen.
+    pub viewport_height: f32,
+    /// How far the rows are scrolled up, between 0 and what shows the last row at the bottom.
+    pub scroll: f32,
+    /// The rows declared this frame.
+    pub rows: Range<usize>,
+}
+
+impl WidgetColors {
+    /// The color of the most specific state: disabled, then pressed, hovered and focused.
+    pub fn pick(&self, response: &WidgetResponse, disabled: bool) -> Color {
+        if disabled {
+            self.disabled
+        } else if response.pressed {
+            self.pressed
+        } else if response.hovered {
+            self.hovered
+        } else if response.focused {
+            self.focused
+        } else {
+            self.idle
+        }
+    }
+}
+
+impl WidgetResponse {
+    /// Any of the responses, for widgets made of several interactive parts.
+    fn merge(self, other: WidgetResponse) -> WidgetResponse {
+        WidgetResponse {
+            hovered: self.hovered || other.hovered,
+            pressed: self.pressed || other.pressed,
+            focused: self.focused || other.focused,
+            clicked: self.clicked || other.clicked,
+            changed: self.changed || other.changed,
+        }
+    }
+}
+
+impl WidgetConfig {
+    fn label_config(&self) -> TextConfig {
+        TextConfig {
+            width: DimensionConfig::fit(),
+            font_id: self.font_id,
+            font_size: self.font_size,
+            font_color: self.font_color,
+            break_word: false,
+            ..Default::default()
+        }
+    }
+
+    /// The interactive element of a widget, sized and padded by the configuration.
+    fn element_config(&self, id: ElementId, color: Color) -> ElementConfig {
+        ElementConfig {
+            id: Some(id),
+            width: self.width,
+            height: self.height,
+            padding: self.padding,
+            child_gap: self.font_size as f32 / 2.,
+            child_alignment: AlignmentConfig {
+                align_x: HorizontalAlignment::Left,
+                align_y: VerticalAlignment::Center,
+            },
+            focus: (!self.disabled).then_some(FocusConfig::default()),
+            color,
+            ..Default::default()
+        }
+    }
+}
+
+impl LayoutContext {
+    pub fn button(&mut self, id: ElementId, label: &str, config: &WidgetConfig) -> WidgetResponse {
+        let response = self.interact(id, config.disabled);
+
+        self.add_element(
+            ElementConfig::new(ElementConfig {
+                child_alignment: AlignmentConfig {
+                    align_x: HorizontalAlignment::Center,
+                    align_y: VerticalAlignment::Center,
+                },
+                ..config.element_config(id, config.colors.pick(&response, config.disabled))
+            }),
+            |ctx| ctx.add_text(label, config.label_config()),
+        );
+
+        response
+    }
+
+    pub fn checkbox(
+        &mut self,
+        id: ElementId,
+        label: &str,
+        checked: &mut bool,
+        config: &WidgetConfig,
+    ) -> WidgetResponse {
+        let mut response = self.interact(id, config.disabled);
+
+        if response.clicked {
+            *checked = !*checked;
+            response.changed = true;
+        }
+
+        let color = config.colors.pick(&response, config.disabled);
+        let mark = checked.then_some(config.accent_color);
+        self.add_element(
+            ElementConfig::new(config.element_config(id, TRANSPARENT)),
+            |ctx| {
+                ctx.mark_box(config.font_size as f32, color, mark);
+                ctx.add_text(label, config.label_config());
+            },
+        );
+
+        response
+    }
+
+    /// A column of options, of which `selected` is the chosen one. Each option can take focus.
+    pub fn radio_group(
+        &mut self,
+        id: ElementId,
+        options: &[&str],
+        selected: &mut usize,
+        config: &WidgetConfig,
+    ) -> WidgetResponse {
+        let mut group_response = WidgetResponse::default();
+
+        self.add_element(
+            ElementConfig::new(ElementConfig {
+                id: Some(id),
+                child_layout_direction: LayoutDirection::TopToBottom,
+                child_gap: config.font_size as f32 / 4.,
+                color: TRANSPARENT,
+                ..Default::default()
+            }),
+            |ctx| {
+                for (index, option) in options.iter().enumerate() {
+                    let option_id = id.nth_child(index);
+                    let mut response = ctx.interact(option_id, config.disabled);
+
+                    if response.clicked && *selected != index {
+                        *selected = index;
+                        response.changed = true;
+                    }
+
+                    let color = config.colors.pick(&response, config.disabled);
+                    let mark = (*selected == index).then_some(config.accent_color);
+                    ctx.add_element(
+                        ElementConfig::new(config.element_config(option_id, TRANSPARENT)),
+                        |ctx| {
+                            ctx.mark_box(config.font_size as f32, color, mark);
+                            ctx.add_text(option, config.label_config());
+                        },
+                    );
+
+                    group_response = group_response.merge(response);
+                }
+            },
+        );
+
+        group_response
+    }
+
+    /// A switch with a knob sitting on the left when off and on the right when on.
+    pub fn toggle(
+        &mut self,
+        id: ElementId,
+        label: &str,
+        on: &mut bool,
+        config: &WidgetConfig,
+    ) -> WidgetResponse {
+        let mut response = self.interact(id, config.disabled);
+
+        if response.clicked {
+            *on = !*on;
+            response.changed = true;
+        }
+
+        let size = config.font_size as f32;
+        let (align_x, knob_color) = if *on {
+            (HorizontalAlignment::Right, config.accent_color)
+        } else {
+            (HorizontalAlignment::Left, config.font_color)
+        };
+
+        let track_color = config.colors.pick(&response, config.disabled);
+        self.add_element(
+            ElementConfig::new(config.element_config(id, TRANSPARENT)),
+            |ctx| {
+                ctx.add_element(
+                    ElementConfig::new(ElementConfig {
+                        width: DimensionConfig::fixed(size * 2.),
+                        height: DimensionConfig::fixed(size),
+                        padding: PaddingConfig::same_padding(size / 8.),
+                        child_alignment: AlignmentConfig {
+                            align_x,
+                            align_y: VerticalAlignment::Center,
+                        },
+                        color: track_color,
+                        ..Default::default()
+                    }),
+                    |ctx| {
+                        ctx.add_element(
+                            ElementConfig::new(ElementConfig {
+                                width: DimensionConfig::fixed(size * 0.75),
+                                height: DimensionConfig::fixed(size * 0.75),
+                                color: knob_color,
+                                ..Default::default()
+                            }),
+                            |_| {},
+                        );
+                    },
+                );
+                ctx.add_text(label, config.label_config());
+            },
+        );
+
+        response
+    }
+
+    /**
+     * A horizontal slider for a value within `range`, as wide as configured. A fit width
+     * makes it ten knobs long, within the fit's min and max. Dragging sets the value under
+     * the pointer, and Left and Right step through a twentieth of the range while it has
+     * focus (Up and Down still move the focus). A mirrored slider runs from right to left,
+     * and so do its arrow keys.
+     */
+    pub fn slider(
+        &mut self,
+        id: ElementId,
+        value: &mut f32,
+        range: RangeInclusive<f32>,
+        config: &WidgetConfig,
+    ) -> WidgetResponse {
+        let mut response = self.interact(id, config.disabled);
+
+        let (min, max) = (*range.start(), *range.end());
+        let knob_size = config.font_size as f32;
+        let previous_value = *value;
+
+        // the track's size is only known from the last layout.
+        let track = self
+            .element_map
+            .get(&id)
+            .map(|element| element.borrow().bounding_box());
+        let padding = config.padding.left + config.padding.right;
+        let travel = track.map_or(0., |track| (track.width - padding - knob_size).max(0.));
+
+        if let Some(track) = track
+            && response.pressed
+            && travel > 0.
+        {
+            // a mirrored slider fills from the right, where its left padding went.
+            let along = if self.mirrored {
+                track.x + track.width - self.pointer.position.x
+            } else {
+                self.pointer.position.x - track.x
+            };
+            let fraction = ((along - config.padding.left - knob_size / 2.) / travel).clamp(0., 1.);
+            *value = min + fraction * (max - min);
+        }
+
+        if response.focused {
+            let (increase_key, decrease_key) = if self.mirrored {
+                (Key::Left, Key::Right)
+            } else {
+                (Key::Right, Key::Left)
+            };
+            let step = (max - min) / 20.;
+            let steps = self.take_key(increase_key) as f32 - self.take_key(decrease_key) as f32;
+            *value = (*value + steps * step).clamp(min, max);
+        }
+
+        response.changed = *value != previous_value;
+
+        let fraction = if max > min {
+            ((*value - min) / (max - min)).clamp(0., 1.)
+        } else {
+            0.
+        };
+
+        // a fit width has only the knob to fit, so the track is given a length of its own.
+        let width = match config.width {
+            DimensionConfig::Fit(_) => {
+                DimensionConfig::fixed(config.width.clamp(knob_size * 10. + padding))
+            }
+            width => width,
+        };
+
+        let track_color = config.colors.pick(&response, config.disabled);
+        self.add_element(
+            ElementConfig::new(ElementConfig {
+                width,
+                child_gap: 0.,
+                ..config.element_config(id, track_color)
+            }),
+            |ctx| {
+                // the filled part pushes the knob along the track.
+                ctx.add_element(
+                    ElementConfig::new(ElementConfig {
+                        width: DimensionConfig::fixed(travel * fraction),
+                        height: DimensionConfig::fixed(knob_size / 4.),
+                        color: config.accent_color,
+                        ..Default::default()
+                    }),
+                    |_| {},
+                );
+                ctx.add_element(
+                    ElementConfig::new(ElementConfig {
+                        width: DimensionConfig::fixed(knob_size),
+                        height: DimensionConfig::fixed(knob_size),
+                        color: config.accent_color,
+                        ..Default::default()
+                    }),
+                    |_| {},
+                );
+            },
+        );
+
+        response
+    }
+
+    /**
+     * A button showing the selected option, which opens the list of options under it, or
+     * above it when there is no room below. While the list is open, Up and Down move through
+     * the options and Enter picks one. Escape or a press anywhere else closes it.
+     */
+    pub fn dropdown(
+        &mut self,
+        id: ElementId,
+        options: &[&str],
+        selected: &mut usize,
+        config: &WidgetConfig,
+    ) -> WidgetResponse {
+        // an open list takes the keys before the button would.
+        let mut picked = self.menu_keys(id, options.len());
+        let mut response = self.interact(id, config.disabled);
+
+        // clicking the button of an open dropdown closes it.
+        if response.clicked && self.open_menus.remove(&id).is_none() {
+            self.open_menus.insert(
+                id,
+                MenuState {
+                    highlighted: *selected,
+                    anchor: Position::default(),
+                    opening: true,
+                    declared: false,
+                },
+            );
+        }
+
+        let floating = FloatingConfig {
+            attach_to: FloatingAttach::Parent,
+            element_point: AttachPoint::LeftTop,
+            parent_point: AttachPoint::LeftBottom,
+            fit_in_root: true,
+            ..Default::default()
+        };
+
+        let color = config.colors.pick(&response, config.disabled);
+        let label = options.get(*selected).copied().unwrap_or_default();
+        self.add_element(
+            ElementConfig::new(config.element_config(id, color)),
+            |ctx| {
+                ctx.add_text(label, config.label_config());
+                picked = picked.or(ctx.menu_list(id, options, floating, config));
+            },
+        );
+
+        if let Some(index) = picked {
+            response.changed = *selected != index;
+            *selected = index;
+        }
+
+        response
+    }
+
+    /**
+     * A menu opening where the secondary pointer button is pressed over `target`. It is
+     * navigated and closed like the list of a dropdown. Returns the option picked this frame.
+     */
+    pub fn context_menu(
+        &mut self,
+        id: ElementId,
+        target: ElementId,
+        options: &[&str],
+        config: &WidgetConfig,
+    ) -> Option<usize> {
+        if self.pointer.secondary_pressed && self.pointer_over(target) {
+            self.open_menus.insert(
+                id,
+                MenuState {
+                    highlighted: 0,
+                    anchor: self.pointer.position,
+                    opening: true,
+                    declared: false,
+                },
+            );
+        }
+
+        let anchor = self.open_menus.get(&id)?.anchor;
+        let picked = self.menu_keys(id, options.len());
+
+        let floating = FloatingConfig {
+            attach_to: FloatingAttach::Point(anchor),
+            fit_in_root: true,
+            ..Default::default()
+        };
+
+        picked.or(self.menu_list(id, options, floating, config))
+    }
+
+    /**
+     * Tabs labeled `labels` over the panel of the selected one, which `panel` declares the
+     * children of given the index of the tab. The selection is kept under `id` and starts
+     * on the first tab. Left and Right switch from a focused tab to its neighbors.
+     */
+    pub fn tabs<F: FnOnce(&mut LayoutContext, usize)>(
+        &mut self,
+        id: ElementId,
+        labels: &[&str],
+        tab_width: TabWidth,
+        config: &WidgetConfig,
+        panel: F,
+    ) -> WidgetResponse {
+        let tab_count = labels.len();
+        let previous = self
+            .selected_tabs
+            .get(&id)
+            .map_or(0, |selection| selection.index)
+            .min(tab_count.saturating_sub(1));
+        let mut selected = previous;
+
+        // every tab is looked at before any is declared, so they all show the same selection.
+        let mut responses = Vec::with_capacity(tab_count);
+        for index in 0..tab_count {
+            let response = self.interact(id.nth_child(index), config.disabled);
+
+            if response.clicked {
+                selected = index;
+            }
+
+            if response.focused {
+                // the tabs run from right to left in a mirrored layout.
+                let (next_key, previous_key) = if self.mirrored {
+                    (Key::Left, Key::Right)
+                } else {
+                    (Key::Right, Key::Left)
+                };
+                let steps = self.take_key(next_key) as isize - self.take_key(previous_key) as isize;
+
+                if steps != 0 {
+                    selected = (index as isize + steps).rem_euclid(tab_count as isize) as usize;
+                    self.focused = Some(id.nth_child(selected));
+                }
+            }
+
+            responses.push(response);
+        }
+
+        self.selected_tabs.insert(
+            id,
+            TabSelection {
+                index: selected,
+                declared: true,
+            },
+        );
+
+        let padding = config.padding.left + config.padding.right;
+        let (header_width, width, tabs_width) = match tab_width {
+            TabWidth::Equal => {
+                let width = self.widest_label(labels, config) + padding;
+                let tabs_width = width * tab_count as f32;
+                (
+                    DimensionConfig::grow_clamped(tabs_width, 0.),
+                    DimensionConfig::grow_clamped(width, 0.),
+                    tabs_width,
+                )
+            }
+            TabWidth::Fit => {
+                let tabs_width = labels
+                    .iter()
+                    .map(|label| self.widest_label(&[label], config) + padding)
+                    .sum();
+                (DimensionConfig::fit(), DimensionConfig::fit(), tabs_width)
+            }
+        };
+
+        // the panel fills a container sized from outside, and is at least as wide as the tabs
+        // in one that fits it.
+        let panel_width = match config.width {
+            DimensionConfig::Fit(_) => DimensionConfig::fit_clamped(tabs_width),
+            _ => DimensionConfig::grow(),
+        };
+        let panel_height = match config.height {
+            DimensionConfig::Fit(_) => DimensionConfig::fit(),
+            _ => DimensionConfig::grow(),
+        };
+
+        self.add_element(
+            ElementConfig::new(ElementConfig {
+                id: Some(id),
+                width: config.width,
+                height: config.height,
+                child_layout_direction: LayoutDirection::TopToBottom,
+                color: TRANSPARENT,
+                ..Default::default()
+            }),
+            |ctx| {
+                ctx.add_element(
+                    ElementConfig::new(ElementConfig {
+                        id: Some(id.child(TAB_HEADER)),
+                        width: header_width,
+                        color: TRANSPARENT,
+                        ..Default::default()
+                    }),
+                    |ctx| {
+                        for (index, label) in labels.iter().enumerate() {
+                            let color = if index == selected {
+                                config.accent_color
+                            } else {
+                                config.colors.pick(&responses[index], config.disabled)
+                            };
+
+                            ctx.add_element(
+                                ElementConfig::new(ElementConfig {
+                                    width,
+                                    height: DimensionConfig::fit(),
+                                    child_alignment: AlignmentConfig::new(
+                                        HorizontalAlignment::Center,
+                                        VerticalAlignment::Center,
+                                    ),
+                                    ..config.element_config(id.nth_child(index), color)
+                                }),
+                                |ctx| ctx.add_text(label, config.label_config()),
+                            );
+                        }
+                    },
+                );
+
+                ctx.add_element(
+                    ElementConfig::new(ElementConfig {
+                        id: Some(id.child(TAB_PANEL)),
+                        width: panel_width,
+                        height: panel_height,
+                        padding: config.padding,
+                        child_layout_direction: LayoutDirection::TopToBottom,
+                        color: config.colors.idle,
+                        ..Default::default()
+                    }),
+                    |ctx| panel(ctx, selected),
+                );
+            },
+        );
+
+        let mut response = responses
+            .into_iter()
+            .fold(WidgetResponse::default(), WidgetResponse::merge);
+        response.changed = selected != previous;
+
+        response
+    }
+
+    /**
+     * Selected tab of the tab container `id`, `None` until it is first declared or set, and
+     * again once a frame went by without it.
+     */
+    pub fn selected_tab(&self, id: ElementId) -> Option<usize> {
+        self.selected_tabs.get(&id).map(|selection| selection.index)
+    }
+
+    /// Select a tab of the container `id`, which is kept until a frame goes by without it.
+    pub fn set_selected_tab(&mut self, id: ElementId, index: usize) {
+        self.selected_tabs.insert(
+            id,
+            TabSelection {
+                index,
+                declared: true,
+            },
+        );
+    }
+
+    /**
+     * A scrolling list of `row_count` rows, where `row` declares the children of a row given
+     * its index. Only the rows seen through the list are declared, so the count can be
+     * very large. The list scrolls with the wheel while the pointer is over it, and keeps
+     * its scroll position and the heights of estimated rows under `id`. A row starting above
+     * the view that turns out taller or shorter than estimated moves the scroll along, so
+     * what is seen stays in place.
+     */
+    pub fn virtual_list<F: FnMut(&mut LayoutContext, usize)>(
+        &mut self,
+        id: ElementId,
+        row_count: usize,
+        config: &VirtualListConfig,
+        mut row: F,
+    ) -> VirtualListResponse {
+        let mut state = self.virtual_lists.remove(&id).unwrap_or_default();
+
+        // rows declared in the last layout have their real height now.
+        if let RowHeight::Estimated(estimate) = config.row_height {
+            state.resize(row_count, estimate);
+
+            let scroll = state.scroll;
+            let mut top = state.height_before(state.rows.start.min(row_count));
+            for index in state.rows.start..state.rows.end.min(row_count) {
+                let previous_height = state.heights[index];
+
+                if let Some(element) = self.element_map.get(&id.nth_child(index)) {
+                    let height = element.borrow().dimensions.height;
+                    state.set_height(index, height);
+
+                    // rows starting above the view push what is seen along, the scroll follows.
+                    if top < scroll {
+                        state.scroll += height - previous_height;
+                    }
+                }
+
+                top += previous_height;
+            }
+        }
+
+        // the list's height is only known from the last layout, until then the root's is used.
+        let viewport_height = self
+            .element_map
+            .get(&id)
+            .map_or(self.root_dimensions.height, |element| {
+                element.borrow().dimensions.height
+            });
+
+        let content_height = match config.row_height {
+            RowHeight::Fixed(height) => height * row_count as f32,
+            RowHeight::Estimated(_) => state.height_before(row_count),
+        };
+
+        if self.pointer_over(id) {
+            state.scroll += self.pointer.scroll.y;
+        }
+        state.scroll = state.scroll.min(content_height - viewport_height).max(0.);
+
+        let (rows, rows_top) = match config.row_height {
+            RowHeight::Fixed(height) if height > 0. => {
+                let first = ((state.scroll / height) as usize).min(row_count);
+                let end = (((state.scroll + viewport_height) / height).ceil() as usize)
+                    .clamp(first, row_count);
+                (first..end, first as f32 * height)
+            }
+            RowHeight::Fixed(_) => (0..0, 0.),
+            RowHeight::Estimated(_) => state.visible_rows(viewport_height),
+        };
+
+        let row_height = match config.row_height {
+            RowHeight::Fixed(height) => DimensionConfig::fixed(height),
+            RowHeight::Estimated(_) => DimensionConfig::fit(),
+        };
+
+        self.add_element(
+            ElementConfig::new(ElementConfig {
+                id: Some(id),
+                width: config.width,
+                height: config.height,
+                child_layout_direction: LayoutDirection::TopToBottom,
+                clip: Some(ClipConfig {
+                    child_offset: Position {
+                        x: 0.,
+                        y: rows_top - state.scroll,
+                    },
+                }),
+                color: config.color,
+                ..Default::default()
+            }),
+            |ctx| {
+                for index in rows.clone() {
+                    ctx.add_element(
+                        ElementConfig::new(ElementConfig {
+                            id: Some(id.nth_child(index)),
+                            width: DimensionConfig::grow(),
+                            height: row_height,
+                            color: TRANSPARENT,
+                            ..Default::default()
+                        }),
+                        |ctx| row(ctx, index),
+                    );
+                }
+            },
+        );
+
+        let response = VirtualListResponse {
+            content_height,
+            viewport_height,
+            scroll: state.scroll,
+            rows: rows.clone(),
+        };
+
+        state.rows = rows;
+        state.declared = true;
+        self.virtual_lists.insert(id, state);
+
+        response
+    }
+
+    /**
+     * Scroll the virtual list `id`, such as from a scrollbar. It is kept in range once
+     * declared, and dropped if a frame goes by without the list.
+     */
+    pub fn set_virtual_list_scroll(&mut self, id: ElementId, scroll: f32) {
+        let state = self.virtual_lists.entry(id).or_default();
+        state.scroll = scroll;
+        state.declared = true;
+    }
+
+    /**
+     * Show `text` next to the element `target` once the pointer stayed over it for the delay,
+     * until it leaves. It moves to the other side of the target rather than out of the root,
+     * and lets the pointer through to what is under it. The time comes from
+     * `set_frame_time`. Returns whether the tooltip shows.
+     */
+    pub fn tooltip(&mut self, target: ElementId, text: &str, config: &TooltipConfig) -> bool {
+        if !self.pointer_over(target) {
+            self.hover_times.remove(&target);
+            return false;
+        }
+
+        let hover_time = self.hover_times.entry(target).or_insert(HoverTime {
+            seconds: 0.,
+            declared: false,
+        });
+        hover_time.seconds += self.frame_time;
+        hover_time.declared = true;
+
+        if hover_time.seconds < config.delay {
+            return false;
+        }
+
+        self.add_element(
+            ElementConfig::new(ElementConfig {
+                id: Some(target.child(TOOLTIP)),
+                padding: config.padding,
+                color: config.color,
+                floating: Some(FloatingConfig {
+                    attach_to: FloatingAttach::Element(target),
+                    element_point: config.element_point,
+                    parent_point: config.target_point,
+                    offset: config.offset,
+                    z_index: i16::MAX,
+                    fit_in_root: true,
+                    capture_pointer: false,
+                }),
+                ..Default::default()
+            }),
+            |ctx| {
+                ctx.add_text(
+                    text,
+                    TextConfig {
+                        font_id: config.font_id,
+                        font_size: config.font_size,
+                        font_color: config.font_color,
+                        ..Default::default()
+                    },
+                );
+            },
+        );
+
+        true
+    }
+
+    /**
+     * Hover and press state of the widget `id`, and whether it was clicked. Pressing
+     * a widget also gives it focus.
+     */
+    fn interact(&mut self, id: ElementId, disabled: bool) -> WidgetResponse {
+        if disabled {
+            return WidgetResponse::default();
+        }
+
+        let hovered = self.pointer_over(id);
+        if hovered && self.pointer.pressed {
+            self.active = Some(id);
+            self.focused = Some(id);
+        }
+
+        let held = self.active == Some(id);
+        let focused = self.focused == Some(id);
+        let clicked = (held && self.pointer.released && hovered)
+            || (focused && self.take_key(Key::Enter) > 0);
+
+        WidgetResponse {
+            hovered,
+            pressed: held && self.pointer.down,
+            focused,
+            clicked,
+            changed: false,
+        }
+    }
+
+    /// Keyboard navigation of the open menu of `owner`. Returns the option picked with Enter.
+    fn menu_keys(&mut self, owner: ElementId, option_count: usize) -> Option<usize> {
+        let mut state = *self.open_menus.get(&owner)?;
+
+        let steps = self.take_key(Key::Down) as isize - self.take_key(Key::Up) as isize;
+        if option_count > 0 {
+            state.highlighted =
+                (state.highlighted as isize + steps).rem_euclid(option_count as isize) as usize;
+        }
+
+        let picked = (self.take_key(Key::Enter) > 0 && state.highlighted < option_count)
+            .then_some(state.highlighted);
+
+        if picked.is_some() || self.take_key(Key::Escape) > 0 {
+            self.open_menus.remove(&owner);
+        } else {
+            self.open_menus.insert(owner, state);
+        }
+
+        picked
+    }
+
+    /**
+     * The floating list of the open menu of `owner`, with the highlighted option standing
+     * out. Hovering an option highlights it and releasing the pointer on it picks it.
+     * A press outside of the list and of its owner closes it.
+     */
+    fn menu_list(
+        &mut self,
+        owner: ElementId,
+        options: &[&str],
+        floating: FloatingConfig,
+        config: &WidgetConfig,
+    ) -> Option<usize> {
+        let mut state = *self.open_menus.get(&owner)?;
+        let list_id = owner.child(MENU_LIST);
+
+        let pressed = self.pointer.pressed || self.pointer.secondary_pressed;
+        if pressed && !state.opening && !self.pointer_over(list_id) && !self.pointer_over(owner) {
+            self.open_menus.remove(&owner);
+            return None;
+        }
+        state.opening = false;
+        state.declared = true;
+
+        // rows grow to the widest of them, which the list fits.
+        let width = self.widest_label(options, config) + config.padding.left + config.padding.right;
+
+        for index in 0..options.len() {
+            if self.pointer_over(list_id.nth_child(index)) {
+                state.highlighted = index;
+
+                if self.pointer.released {
+                    self.open_menus.remove(&owner);
+                    return Some(index);
+                }
+            }
+        }
+        self.open_menus.insert(owner, state);
+
+        self.add_element(
+            ElementConfig::new(ElementConfig {
+                id: Some(list_id),
+                width: DimensionConfig::fit_clamped(width),
+                floating: Some(floating),
+                child_layout_direction: LayoutDirection::TopToBottom,
+                color: config.colors.idle,
+                ..Default::default()
+            }),
+            |ctx| {
+                for (index, option) in options.iter().enumerate() {
+                    let color = if index == state.highlighted {
+                        config.colors.hovered
+                    } else {
+                        config.colors.idle
+                    };
+
+                    ctx.add_element(
+                        ElementConfig::new(ElementConfig {
+                            id: Some(list_id.nth_child(index)),
+                            width: DimensionConfig::grow(),
+                            padding: config.padding,
+                            color,
+                            ..Default::default()
+                        }),
+                        |ctx| ctx.add_text(option, config.label_config()),
+                    );
+                }
+            },
+        );
+
+        None
+    }
+
+    /// Width of the widest of `labels` on a single line.
+    fn widest_label(&mut self, labels: &[&str], config: &WidgetConfig) -> f32 {
+        let label_config = config.label_config();
+
+        labels
+            .iter()
+            .map(|label| {
+                let span = TextSpan {
+                    text: label,
+                    font_id: None,
+                    font_size: None,
+                    font_color: None,
+                };
+                self.natural_text_width(&[span], &label_config)
+            })
+            .fold(0., f32::max)
+    }
+
+    /// The box of a checkbox or radio button, filled with the mark when there is one.
+    fn mark_box(&mut self, size: f32, color: Color, mark: Option<Color>) {
+        self.add_element(
+            ElementConfig::new(ElementConfig {
+                width: DimensionConfig::fixed(size),
+                height: DimensionConfig::fixed(size),
+                padding: PaddingConfig::same_padding(size / 4.),
+                color,
+                ..Default::default()
+            }),
+            |ctx| {
+                if let Some(mark) = mark {
+                    ctx.add_element(
+                        ElementConfig::new(ElementConfig {
+                            width: DimensionConfig::grow(),
+                            height: DimensionConfig::grow(),
+                            color: mark,
+                            ..Default::default()
+                        }),
+                        |_| {},
+                    );
+                }
+            },
+        );
+    }
+}
+
+/*
+    The heights of estimated rows are summed up in a Fenwick tree: `height_sums[i - 1]` holds
+    the sum of the heights of the `i & i.wrapping_neg()` rows ending with row `i - 1`. Any
+    sum of the first rows, and the row at any height, is then found in logarithmic time.
+*/
+impl VirtualListState {
+    /// Keep one height per row, new rows taking the estimate.
+    fn resize(&mut self, row_count: usize, estimate: f32) {
+        if self.heights.len() == row_count {
+            return;
+        }
+
+        self.heights.resize(row_count, estimate);
+        self.height_sums = self.heights.clone();
+
+        for index in 1..=row_count {
+            let parent = index + (index & index.wrapping_neg());
+            if parent <= row_count {
+                self.height_sums[parent - 1] += self.height_sums[index - 1];
+            }
+        }
+    }
+
+    fn set_height(&mut self, row: usize, height: f32) {
+        let difference = height - self.heights[row];
+        self.heights[row] = height;
+
+        let mut index = row + 1;
+        while index <= self.height_sums.len() {
+            self.height_sums[index - 1] += difference;
+            index += index & index.wrapping_neg();
+        }
+    }
+
+    /// Sum of the heights of the rows before `row`.
+    fn height_before(&self, row: usize) -> f32 {
+        let mut sum = 0.;
+        let mut index = row;
+
+        while index > 0 {
+            sum += self.height_sums[index - 1];
+            index -= index & index.wrapping_neg();
+        }
+
+        sum
+    }
+
+    /// The row `y` falls in, or the row count when it is past the last one.
+    fn row_at(&self, y: f32) -> usize {
+        let mut row = 0;
+        let mut top = 0.;
+        let mut step = self
+            .height_sums
+            .len()
+            .checked_next_power_of_two()
+            .unwrap_or(0);
+
+        while step > 0 {
+            let next = row + step;
+            if next <= self.height_sums.len() && top + self.height_sums[next - 1] <= y {
+                row = next;
+                top += self.height_sums[next - 1];
+            }
+            step /= 2;
+        }
+
+        row
+    }
+
+    /// The rows seen through the list at its scroll position, and the top of the first.
+    fn visible_rows(&self, viewport_height: f32) -> (Range<usize>, f32) {
+        let first = self.row_at(self.scroll);
+        let first_top = self.height_before(first);
+
+        let mut end = first;
+        let mut top = first_top;
+        while end < self.heights.len() && top < self.scroll + viewport_height {
+            top += self.heights[end];
+            end += 1;
+        }
+
+        (first..end, first_top)
+    }
+}
+
+impl Default for WidgetColors {
+    fn default() -> Self {
+        WidgetColors {
+            idle: Color {
+                r: 60,
+                g: 60,
+                b: 60,
+                a: 255,
+            },
+            hovered: Color {
+                r: 80,
+                g: 80,
+                b: 80,
+                a: 255,
+            },
+            pressed: Color {
+                r: 40,
+                g: 40,
+                b: 40,
+                a: 255,
+            },
+            focused: Color {
+                r: 70,
+                g: 70,
+                b: 90,
+                a: 255,
+            },
+            disabled: Color {
+                r: 45,
+                g: 45,
+                b: 45,
+                a: 255,
+            },
+        }
+    }
+}
+
+impl Default for WidgetConfig {
+    fn default() -> Self {
+        WidgetConfig {
+            width: DimensionConfig::fit(),
+            height: DimensionConfig::fit(),
+            padding: PaddingConfig::same_padding(4.),
+            font_id: 0,
+            font_size: 16,
+            font_color: Color {
+                r: 255,
+                g: 255,
+                b: 255,
+                a: 255,
+            },
+            colors: WidgetColors::default(),
+            accent_color: Color {
+                r: 70,
+                g: 130,
+                b: 230,
+                a: 255,
+            },
+            disabled: false,
+        }
+    }
+}
+
+impl Default for TooltipConfig {
+    fn default() -> Self {
+        TooltipConfig {
+            delay: 0.5,
+            element_point: AttachPoint::CenterBottom,
+            target_point: AttachPoint::CenterTop,
+            offset: Position { x: 0., y: -4. },
+            padding: PaddingConfig::axis_padding(2., 4.),
+            color: Color {
+                r: 20,
+                g: 20,
+                b: 20,
+                a: 230,
+            },
+            font_id: 0,
+            font_size: 14,
+            font_color: Color {
+                r: 255,
+                g: 255,
+                b: 255,
+                a: 255,
+            },
+        }
+    }
+}
+
+impl Default for VirtualListConfig {
+    fn default() -> Self {
+        VirtualListConfig {
+            width: DimensionConfig::grow(),
+            height: DimensionConfig::grow(),
+            row_height: RowHeight::Fixed(24.),
+            color: TRANSPARENT,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDGET: ElementId = ElementId(1);
+
+    /// Lay out a frame holding what `declare` adds, and return what it gave back.
+    fn frame<T>(ctx: &mut LayoutContext, declare: impl FnOnce(&mut LayoutContext) -> T) -> T {
+        ctx.begin_layout();
+        let result = declare(ctx);
+        ctx.end_layout();
+
+        result
+    }
+
+    /// A context with `focused` holding the focus and `key` pressed.
+    fn pressing(key: Key, focused: ElementId) -> LayoutContext {
+        let mut ctx = context();
+        ctx.set_focus(Some(focused));
+        ctx.push_input(InputEvent::Key { key, shift: false });
+        ctx
+    }
+
+    #[test]
+    fn button_is_clicked_by_a_press_and_release_over_it() {
+        let config = WidgetConfig {
+            width: DimensionConfig::fixed(100.),
+            height: DimensionConfig::fixed(40.),
+            ..Default::default()
+        };
+        let mut ctx = context();
+        frame(&mut ctx, |ctx| ctx.button(WIDGET, "ok", &config));
+
+        ctx.set_pointer_state(Position { x: 10., y: 10. }, true);
+        let response = frame(&mut ctx, |ctx| ctx.button(WIDGET, "ok", &config));
+        assert!(response.pressed && !response.clicked);
+
+        ctx.set_pointer_state(Position { x: 10., y: 10. }, false);
+        let response = frame(&mut ctx, |ctx| ctx.button(WIDGET, "ok", &config));
+        assert!(response.clicked);
+    }
+
+    #[test]
+    fn button_is_clicked_by_enter_unless_disabled() {
+        let config = WidgetConfig::default();
+        let mut ctx = pressing(Key::Enter, WIDGET);
+        assert!(frame(&mut ctx, |ctx| ctx.button(WIDGET, "ok", &config)).clicked);
+
+        let config = WidgetConfig {
+            disabled: true,
+            ..Default::default()
+        };
+        let mut ctx = pressing(Key::Enter, WIDGET);
+        assert!(!frame(&mut ctx, |ctx| ctx.button(WIDGET, "ok", &config)).clicked);
+    }
+
+    #[test]
+    fn checkbox_and_toggle_flip_when_clicked() {
+        let config = WidgetConfig::default();
+
+        let mut checked = false;
+        let mut ctx = pressing(Key::Enter, WIDGET);
+        let response = frame(&mut ctx, |ctx| {
+            ctx.checkbox(WIDGET, "a", &mut checked, &config)
+        });
+        assert!(checked && response.changed);
+
+        let response = frame(&mut ctx, |ctx| {
+            ctx.checkbox(WIDGET, "a", &mut checked, &config)
+        });
+        assert!(checked && !response.changed);
+
+        let mut on = true;
+        let mut ctx = pressing(Key::Enter, WIDGET);
+        let response = frame(&mut ctx, |ctx| ctx.toggle(WIDGET, "a", &mut on, &config));
+        assert!(!on && response.changed);
+    }
+
+    #[test]
+    fn radio_group_selects_the_clicked_option() {
+        let config = WidgetConfig::default();
+        let options = ["a", "b", "c"];
+        let mut selected = 0;
+
+        let mut ctx = pressing(Key::Enter, WIDGET.nth_child(2));
+        let response = frame(&mut ctx, |ctx| {
+            ctx.radio_group(WIDGET, &options, &mut selected, &config)
+        });
+        assert_eq!(selected, 2);
+        assert!(response.clicked && response.changed);
+
+        let mut ctx = pressing(Key::Enter, WIDGET.nth_child(2));
+        let response = frame(&mut ctx, |ctx| {
+            ctx.radio_group(WIDGET, &options, &mut selected, &config)
+        });
+        assert_eq!(selected, 2);
+        assert!(response.clicked && !response.changed);
+    }
+
+    #[test]
+    fn slider_steps_with_the_arrow_keys() {
+        let config = WidgetConfig::default();
+
+        let mut value = 0.5;
+        let mut ctx = pressing(Key::Right, WIDGET);
+        let response = frame(&mut ctx, |ctx| {
+            ctx.slider(WIDGET, &mut value, 0.0..=1., &config)
+        });
+        assert_eq!(value, 0.55);
+        assert!(response.changed);
+
+        let mut value = 0.;
+        let mut ctx = pressing(Key::Left, WIDGET);
+        let response = frame(&mut ctx, |ctx| {
+            ctx.slider(WIDGET, &mut value, 0.0..=1., &config)
+        });
+        assert_eq!(value, 0.);
+        assert!(!response.changed);
+    }
+
+    #[test]
+    fn slider_takes_the_value_under_the_pointer() {
+        let config = WidgetConfig {
+            width: DimensionConfig::fixed(200.),
+            ..Default::default()
+        };
+        let mut value = 0.;
+        let mut ctx = context();
+        frame(&mut ctx, |ctx| {
+            ctx.slider(WIDGET, &mut value, 0.0..=1., &config)
+        });
+
+        // the knob's center travels the track inside the padding.
+        let knob_size = config.font_size as f32;
+        let start = config.padding.left + knob_size / 2.;
+        let travel = 200. - config.padding.left - config.padding.right - knob_size;
+        ctx.set_pointer_state(
+            Position {
+                x: start + travel * 0.75,
+                y: 5.,
+            },
+            true,
+        );
+        let response = frame(&mut ctx, |ctx| {
+            ctx.slider(WIDGET, &mut value, 0.0..=1., &config)
+        });
+
+        assert!((value - 0.75).abs() < 1e-4);
+        assert!(response.changed);
+    }
+
+    #[test]
+    fn slider_with_a_fit_width_has_a_track_to_drag() {
+        let config = WidgetConfig::default();
+        let mut value = 0.;
+        let mut ctx = context();
+        frame(&mut ctx, |ctx| {
+            ctx.slider(WIDGET, &mut value, 0.0..=1., &config)
+        });
+
+        // ten knobs long: the middle of the travel is five knobs in, past the padding.
+        let knob_size = config.font_size as f32;
+        ctx.set_pointer_state(
+            Position {
+                x: config.padding.left + knob_size * 5.,
+                y: 5.,
+            },
+            true,
+        );
+        let response = frame(&mut ctx, |ctx| {
+            ctx.slider(WIDGET, &mut value, 0.0..=1., &config)
+        });
+
+        assert!((value - 0.5).abs() < 1e-4);
+        assert!(response.changed);
+    }
+
+    const TARGET: ElementId = ElementId(1);
+
+    /// Lay out a 100 by 100 target, with its tooltip when `with_tooltip`. Returns whether it showed.
+    fn tooltip_frame(ctx: &mut LayoutContext, with_tooltip: bool) -> bool {
+        ctx.begin_layout();
+        ctx.add_element(
+            ElementConfig::new(ElementConfig {
+                id: Some(TARGET),
+                width: DimensionConfig::fixed(100.),
+                height: DimensionConfig::fixed(100.),
+                ..Default::default()
+            }),
+            |_| {},
+        );
+
+        let shown = with_tooltip && ctx.tooltip(TARGET, "tip", &TooltipConfig::default());
+        ctx.end_layout();
+
+        shown
+    }
+
+    /// A context where every character is 8 wide.
+    fn context() -> LayoutContext {
+        let mut ctx = LayoutContext::create_context(400., 400.);
+        ctx.set_measurement_fn(|text, _, font_size| TextMeasurement {
+            width: text.chars().count() as f32 * 8.,
+            ascent: font_size as f32 * 0.75,
+            descent: font_size as f32 * 0.25,
+            x_offset: 0.,
+        });
+        ctx
+    }
+
+    fn hovering_context() -> LayoutContext {
+        let mut ctx = context();
+        ctx.set_pointer_state(Position { x: 50., y: 50. }, false);
+        ctx.set_frame_time(0.3);
+
+        // the target is only known to be hovered once it was laid out.
+        tooltip_frame(&mut ctx, true);
+        ctx
+    }
+
+    #[test]
+    fn tooltip_shows_once_hovered_for_the_delay() {
+        let mut ctx = hovering_context();
+
+        assert!(!tooltip_frame(&mut ctx, true));
+        assert!(tooltip_frame(&mut ctx, true));
+    }
+
+    #[test]
+    fn tooltip_not_declared_for_a_frame_starts_over() {
+        let mut ctx = hovering_context();
+
+        tooltip_frame(&mut ctx, true);
+        tooltip_frame(&mut ctx, true);
+        tooltip_frame(&mut ctx, false);
+
+        assert!(!tooltip_frame(&mut ctx, true));
+    }
+
+    /// Press Enter with `BUTTON` focused in a frame where a modal opens. Returns whether it was clicked.
+    fn click_with_modal_opening(button_in_modal: bool) -> bool {
+        const MODAL: ElementId = ElementId(2);
+        const BUTTON: ElementId = ElementId(3);
+
+        let mut ctx = context();
+        ctx.set_focus(Some(BUTTON));
+        ctx.push_input(InputEvent::Key {
+            key: Key::Enter,
+            shift: false,
+        });
+
+        let config = WidgetConfig::default();
+        let mut clicked = false;
+        let mut open = true;
+
+        ctx.begin_layout();
+        ctx.add_modal(MODAL, &mut open, ModalConfig::default(), |ctx| {
+            if button_in_modal {
+                clicked = ctx.button(BUTTON, "ok", &config).clicked;
+            }
+        });
+        if !button_in_modal {
+            clicked = ctx.button(BUTTON, "ok", &config).clicked;
+        }
+        ctx.end_layout();
+
+        clicked
+    }
+
+    #[test]
+    fn modal_blocks_keys_on_the_frame_it_opens() {
+        assert!(!click_with_modal_opening(false));
+        assert!(click_with_modal_opening(true));
+    }
+
+    #[test]
+    fn dropdown_not_declared_for_a_frame_comes_back_closed() {
+        const DROPDOWN: ElementId = ElementId(2);
+
+        let config = WidgetConfig::default();
+        let mut selected = 0;
+        let mut ctx = pressing(Key::Enter, DROPDOWN);
+        frame(&mut ctx, |ctx| {
+            ctx.dropdown(DROPDOWN, &["a", "b"], &mut selected, &config)
+        });
+        assert!(ctx.open_menus.contains_key(&DROPDOWN));
+
+        frame(&mut ctx, |_| {});
+        frame(&mut ctx, |ctx| {
+            ctx.dropdown(DROPDOWN, &["a", "b"], &mut selected, &config)
+        });
+        assert!(!ctx.open_menus.contains_key(&DROPDOWN));
+    }
+
+    /// Press `key` on the focused first of three tabs. Returns the tab selected after it.
+    fn tab_after_key(key: Key, mirrored: bool) -> usize {
+        const TABS: ElementId = ElementId(2);
+
+        let mut ctx = context();
+        ctx.set_mirrored(mirrored);
+        ctx.set_focus(Some(TABS.nth_child(0)));
+        ctx.push_input(InputEvent::Key { key, shift: false });
+
+        let mut selected = 0;
+        ctx.begin_layout();
+        ctx.tabs(
+            TABS,
+            &["a", "b", "c"],
+            TabWidth::Fit,
+            &WidgetConfig::default(),
+            |_, index| selected = index,
+        );
+        ctx.end_layout();
+
+        selected
+    }
+
+    #[test]
+    fn tab_keys_follow_the_layout_direction() {
+        assert_eq!(tab_after_key(Key::Right, false), 1);
+        assert_eq!(tab_after_key(Key::Left, false), 2);
+        assert_eq!(tab_after_key(Key::Left, true), 1);
+        assert_eq!(tab_after_key(Key::Right, true), 2);
+    }
+
+    #[test]
+    fn tab_selection_is_forgotten_once_not_declared() {
+        const TABS: ElementId = ElementId(2);
+
+        let config = WidgetConfig::default();
+        let mut ctx = context();
+        ctx.set_selected_tab(TABS, 2);
+
+        let mut shown = 0;
+        frame(&mut ctx, |ctx| {
+            ctx.tabs(
+                TABS,
+                &["a", "b", "c"],
+                TabWidth::Fit,
+                &config,
+                |_, index| shown = index,
+            )
+        });
+        assert_eq!(shown, 2);
+
+        frame(&mut ctx, |_| {});
+        frame(&mut ctx, |_| {});
+        assert_eq!(ctx.selected_tab(TABS), None);
+    }
+
+    #[test]
+    fn slider_runs_from_the_right_when_mirrored() {
+        let config = WidgetConfig {
+            width: DimensionConfig::fixed(200.),
+            ..Default::default()
+        };
+        let mut value = 0.;
+        let mut ctx = context();
+        ctx.set_mirrored(true);
+        frame(&mut ctx, |ctx| {
+            ctx.slider(WIDGET, &mut value, 0.0..=1., &config)
+        });
+
+        // the slider ends at the root's right edge, where its value starts.
+        let knob_size = config.font_size as f32;
+        let start = config.padding.left + knob_size / 2.;
+        let travel = 200. - config.padding.left - config.padding.right - knob_size;
+        ctx.set_pointer_state(
+            Position {
+                x: 400. - start - travel * 0.75,
+                y: 5.,
+            },
+            true,
+        );
+        frame(&mut ctx, |ctx| {
+            ctx.slider(WIDGET, &mut value, 0.0..=1., &config)
+        });
+        assert!((value - 0.75).abs() < 1e-4);
+
+        ctx.set_pointer_state(Position { x: 0., y: 0. }, false);
+        ctx.push_input(InputEvent::Key {
+            key: Key::Left,
+            shift: false,
+        });
+        frame(&mut ctx, |ctx| {
+            ctx.slider(WIDGET, &mut value, 0.0..=1., &config)
+        });
+        assert!((value - 0.8).abs() < 1e-4);
+    }
+
+    #[test]
+    fn row_height_sums_match_the_rows() {
+        let mut state = VirtualListState::default();
+        state.resize(7, 10.);
+        state.set_height(2, 30.);
+        state.set_height(5, 0.);
+
+        for row in 0..=7 {
+            let sum: f32 = state.heights[..row].iter().sum();
+            assert_eq!(state.height_before(row), sum);
+        }
+
+        assert_eq!(state.row_at(0.), 0);
+        assert_eq!(state.row_at(25.), 2);
+        assert_eq!(state.row_at(50.), 3);
+        // the empty row 5 is skipped.
+        assert_eq!(state.row_at(70.), 6);
+        assert_eq!(state.row_at(80.), 7);
+
+        state.scroll = 45.;
+        assert_eq!(state.visible_rows(20.), (2..5, 20.));
+    }
+
+    const LIST: ElementId = ElementId(2);
+
+    /// Lay out a list 100 high of 1000 rows, each actually 20 high.
+    fn list_frame(ctx: &mut LayoutContext, row_height: RowHeight) -> VirtualListResponse {
+        let config = VirtualListConfig {
+            height: DimensionConfig::fixed(100.),
+            row_height,
+            ..Default::default()
+        };
+
+        ctx.begin_layout();
+        let response = ctx.virtual_list(LIST, 1000, &config, |ctx, _| {
+            ctx.add_element(
+                ElementConfig::new(ElementConfig {
+                    height: DimensionConfig::fixed(20.),
+                    ..Default::default()
+                }),
+                |_| {},
+            );
+        });
+        ctx.end_layout();
+
+        response
+    }
+
+    #[test]
+    fn measured_rows_above_the_view_keep_it_in_place() {
+        let mut ctx = context();
+        ctx.set_virtual_list_scroll(LIST, 105.);
+
+        // row 10 starts 5 above the view, and row 11 is 5 below its top.
+        list_frame(&mut ctx, RowHeight::Estimated(10.));
+        let response = list_frame(&mut ctx, RowHeight::Estimated(10.));
+
+        // row 10 is now 20 high, row 11 starts at 120.
+        assert_eq!(response.scroll, 115.);
+    }
+
+    #[test]
+    fn list_not_declared_for_a_frame_starts_over() {
+        let mut ctx = context();
+        ctx.set_virtual_list_scroll(LIST, 40.);
+        assert_eq!(list_frame(&mut ctx, RowHeight::Fixed(20.)).scroll, 40.);
+
+        frame(&mut ctx, |_| {});
+        assert_eq!(list_frame(&mut ctx, RowHeight::Fixed(20.)).scroll, 0.);
+    }
+
+    #[test]
+    fn pointer_scroll_applies_to_one_layout() {
+        let mut ctx = context();
+        let position = Position { x: 50., y: 50. };
+        list_frame(&mut ctx, RowHeight::Fixed(20.));
+
+        ctx.set_pointer_scroll(Position { x: 0., y: 30. });
+        ctx.set_pointer_state(position, false);
+        assert_eq!(list_frame(&mut ctx, RowHeight::Fixed(20.)).scroll, 30.);
+
+        assert_eq!(list_frame(&mut ctx, RowHeight::Fixed(20.)).scroll, 30.);
+        ctx.set_pointer_state(position, false);
+        assert_eq!(list_frame(&mut ctx, RowHeight::Fixed(20.)).scroll, 30.);
+    }
+}